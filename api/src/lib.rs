@@ -0,0 +1,554 @@
+//! Request and response types of the API, shared by the server and the client
+//!
+//! With the `server` feature, they also map to database rows and describe
+//! themselves in the OpenAPI document.
+
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+
+// their SQL uses `INT` but not `INT UNSIGNED`
+pub type RefId = i32;
+
+/// Envelope of the JSON responses; `code` is 0 on success and 1 on failure
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct ResponseJson<D> {
+    pub data: Option<D>,
+    pub code: u32,
+    pub message: Option<String>,
+}
+
+impl<D> ResponseJson<D> {
+    pub fn ok(data: D) -> Self {
+        Self {
+            data: Some(data),
+            code: 0,
+            message: None,
+        }
+    }
+
+    pub fn error() -> Self {
+        Self {
+            data: None,
+            code: 1,
+            message: None,
+        }
+    }
+
+    pub fn error_msg<S: Into<String>>(message: S) -> Self {
+        Self {
+            data: None,
+            code: 1,
+            message: Some(message.into()),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl<D: Serialize> axum::response::IntoResponse for ResponseJson<D> {
+    fn into_response(self) -> axum::response::Response {
+        axum::Json(self).into_response()
+    }
+}
+
+/// `data` of the responses that carry none, i.e. always `null`; for the
+/// OpenAPI document only
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct Empty;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct InspectionForm {
+    pub creator: RefId,
+    pub device_code: i32,
+    pub device_category: String,
+    pub creation_time: String,
+    pub product_spec: Option<String>,
+    pub wire_number: Option<i32>,
+    pub wire_type: Option<String>,
+    pub break_spec: String,
+    pub wire_batch_code: Option<String>,
+    pub stick_batch_code: Option<String>,
+    pub warehouse: Option<String>,
+    /// 是否拉丝池内断线
+    pub break_flag: bool,
+    /// 拉丝池 BigDecimal
+    pub breakpoint_b: Option<String>,
+    /// 非拉丝池 ref
+    pub breakpoint_a: Option<RefId>,
+    /// 初检原因
+    pub break_cause_a: Option<RefId>,
+    pub comments: Option<String>,
+    pub product_time: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "server", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct InspectionDetails {
+    pub id: i64,
+    pub device_code: i32,
+    pub device_category: String,
+    /// ref
+    #[cfg_attr(feature = "server", sqlx(flatten))]
+    pub creator: User,
+    pub creation_time: String,
+    pub inspection_flag: i32,
+    pub product_spec: Option<String>,
+    pub wire_num: Option<i32>,
+    pub wire_type: Option<String>,
+    pub break_spec: String,
+    pub wire_batch_code: Option<String>,
+    pub stick_batch_code: Option<String>,
+    pub warehouse: Option<String>,
+    pub product_time: Option<String>,
+    pub break_flag: bool,
+    #[cfg_attr(feature = "server", schema(value_type = Option<String>))]
+    pub breakpoint_b: Option<BigDecimal>,
+    /// ref
+    #[cfg_attr(feature = "server", sqlx(skip))]
+    pub breakpoint_a: Option<Breakpoint>,
+    /// ref
+    #[cfg_attr(feature = "server", sqlx(skip))]
+    pub break_cause_a: Option<BreakCause>,
+    /// ref
+    #[cfg_attr(feature = "server", sqlx(skip))]
+    pub break_cause_b: Option<BreakCause>,
+    pub comments: Option<String>,
+    /// ref
+    #[cfg_attr(feature = "server", sqlx(skip))]
+    pub inspector: Option<User>,
+    pub inspection_time: Option<String>,
+    #[cfg_attr(feature = "server", sqlx(skip))]
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "server", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct InspectionSummary {
+    pub id: i64,
+    pub device_code: i32,
+    #[cfg_attr(feature = "server", sqlx(skip))]
+    pub break_cause_a: Option<BreakCause>,
+    #[cfg_attr(feature = "server", sqlx(skip))]
+    pub break_cause_b: Option<BreakCause>,
+    pub break_flag: bool,
+    pub break_spec: String,
+    pub product_spec: Option<String>,
+    #[cfg_attr(feature = "server", sqlx(flatten))]
+    pub creator: User,
+    pub creation_time: String,
+    /// 0: 已初检 1: 已终检
+    pub inspection_flag: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[cfg_attr(feature = "server", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "server", into_params(parameter_in = Query))]
+pub struct SearchQuery {
+    pub filter: String,
+    pub stage: u32,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "server", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    #[cfg_attr(feature = "server", sqlx(rename = "user_id"))]
+    pub id: i32,
+    #[cfg_attr(feature = "server", sqlx(rename = "user_name"))]
+    pub name: String,
+    #[cfg_attr(feature = "server", sqlx(rename = "user_enable_state"))]
+    pub enable_state: i32,
+    #[cfg_attr(feature = "server", sqlx(rename = "user_user_type"))]
+    pub user_type: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "server", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Breakpoint {
+    #[cfg_attr(feature = "server", sqlx(rename = "bp_id"))]
+    pub id: i32,
+    #[cfg_attr(feature = "server", sqlx(rename = "bp_name"))]
+    pub breakpoint: Option<String>,
+    #[cfg_attr(feature = "server", sqlx(rename = "bp_enable_state"))]
+    pub enable_state: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "server", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct BreakCause {
+    #[cfg_attr(feature = "server", sqlx(rename = "cause_id"))]
+    pub id: i32,
+    #[cfg_attr(feature = "server", sqlx(rename = "cause_type"))]
+    pub r#type: Option<String>,
+    #[cfg_attr(feature = "server", sqlx(rename = "cause_name"))]
+    pub cause: Option<String>,
+    #[cfg_attr(feature = "server", sqlx(rename = "cause_enable_state"))]
+    pub enable_state: i32,
+}
+
+/// A photo attached to an inspection
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "server", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: i64,
+    pub inspection_id: i64,
+    /// Original filename from the client
+    pub filename: Option<String>,
+    pub content_type: String,
+    pub size: i64,
+    pub width: i32,
+    pub height: i32,
+    pub creation_time: String,
+    /// Server side only
+    #[serde(skip)]
+    pub object_key: String,
+    /// Server side only
+    #[serde(skip)]
+    pub thumbnail_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[cfg_attr(feature = "server", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "server", into_params(parameter_in = Query))]
+pub struct HistoryQuery {
+    /// Lower bound of `creationtime`, inclusive
+    pub from: Option<String>,
+    /// Upper bound of `creationtime`, inclusive
+    pub to: Option<String>,
+    /// Max number of breaks in the timeline
+    pub limit: Option<u64>,
+    /// Max number of entries in the cause/breakpoint rankings
+    pub top: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct MachineHistory {
+    pub device_code: i32,
+    pub stage: i32,
+    /// Number of breaks within the window
+    pub break_count: i64,
+    /// Mean time between breaks within the window, in seconds
+    ///
+    /// `None` if there are less than two breaks.
+    pub mtbf_secs: Option<f64>,
+    pub breaks: Vec<MachineBreak>,
+    pub top_causes: Vec<CauseFrequency>,
+    pub top_breakpoints: Vec<BreakpointFrequency>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "server", derive(sqlx::FromRow, utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct MachineBreak {
+    pub id: i64,
+    pub creation_time: String,
+    /// 0: 已初检 1: 已终检
+    pub inspection_flag: i32,
+    pub break_flag: bool,
+    pub break_spec: String,
+    #[cfg_attr(feature = "server", schema(value_type = Option<String>))]
+    pub breakpoint_b: Option<BigDecimal>,
+    /// ref
+    #[cfg_attr(feature = "server", sqlx(skip))]
+    pub breakpoint_a: Option<Breakpoint>,
+    /// ref
+    #[cfg_attr(feature = "server", sqlx(skip))]
+    pub break_cause_a: Option<BreakCause>,
+    /// ref
+    #[cfg_attr(feature = "server", sqlx(skip))]
+    pub break_cause_b: Option<BreakCause>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "server", derive(sqlx::FromRow, utoipa::ToSchema))]
+pub struct CauseFrequency {
+    #[cfg_attr(feature = "server", sqlx(flatten))]
+    pub cause: BreakCause,
+    pub count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "server", derive(sqlx::FromRow, utoipa::ToSchema))]
+pub struct BreakpointFrequency {
+    #[cfg_attr(feature = "server", sqlx(flatten))]
+    pub breakpoint: Breakpoint,
+    pub count: i64,
+}
+
+/// An uploaded client log
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct LogMetadata {
+    /// `<unix millis>-<random>`; sorts by the received time
+    pub id: String,
+    pub device: Option<String>,
+    pub user: Option<String>,
+    pub app_version: Option<String>,
+    pub original_filename: Option<String>,
+    pub size: u64,
+    /// RFC 3339
+    pub received_time: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[cfg_attr(feature = "server", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "server", into_params(parameter_in = Query))]
+#[serde(rename_all = "camelCase")]
+pub struct ListQuery {
+    pub device: Option<String>,
+    pub user: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+pub struct Pong {
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadReport {
+    /// Changed settings that have taken effect
+    pub applied: Vec<String>,
+    /// Changed settings that are ignored until the server restarts
+    pub restart_required: Vec<String>,
+}
+
+/// An inspection created or edited on a tablet, possibly while offline
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SyncItem {
+    /// UUID generated by the client when creating the inspection, and kept
+    /// for its later edits
+    pub client_id: String,
+    /// `version` of the inspection the edit is based on, as the client saw
+    /// it last; `None` for new inspections
+    pub base_version: Option<i64>,
+    pub form: InspectionForm,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum SyncStatus {
+    Created,
+    Updated,
+    /// The same form has been pushed already; nothing is changed.
+    Unchanged,
+    /// The inspection has been changed by others since the client's last
+    /// push, and `base_version` is older than that. The server's copy is
+    /// kept, and the edit is dropped.
+    Conflict,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SyncResult {
+    pub client_id: String,
+    /// Id assigned by the server
+    pub id: i64,
+    pub status: SyncStatus,
+    /// Current version of the inspection
+    pub version: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[cfg_attr(feature = "server", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "server", into_params(parameter_in = Query))]
+pub struct ChangesQuery {
+    /// `version` of the last response; 0 or none for everything
+    pub since: Option<i64>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Changes {
+    /// The oldest change first; each inspection appears once, with its
+    /// latest state
+    pub changes: Vec<InspectionChange>,
+    /// Token for the next `since`
+    pub version: i64,
+    /// Whether there are more changes after `version`
+    pub more: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct InspectionChange {
+    pub version: i64,
+    /// Set if the inspection was pushed by a client
+    pub client_id: Option<String>,
+    /// Soft-deleted
+    pub deleted: bool,
+    pub inspection: InspectionDetails,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[cfg_attr(feature = "server", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "server", into_params(parameter_in = Query))]
+#[serde(rename_all = "camelCase")]
+pub struct ImportQuery {
+    /// Validate and insert, then roll back
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Extra column names, like `原因=breakCauseA,班组备注=comments`
+    pub map: Option<String>,
+}
+
+/// Outcome of an import. Nothing is imported if there are any errors.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub dry_run: bool,
+    /// Data rows read, not counting the header and blank rows
+    pub rows: usize,
+    /// Inspections imported, or that would be imported on dry runs
+    pub imported: usize,
+    /// Ids of the imported inspections in row order; empty on dry runs
+    pub ids: Vec<i64>,
+    pub errors: Vec<ImportError>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ImportError {
+    /// As numbered in the spreadsheet, where the header is row 1
+    pub row: usize,
+    pub column: Option<String>,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "server", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "server", into_params(parameter_in = Query))]
+pub struct SummaryQuery {
+    pub stage: u32,
+    /// First day, like `2024-01-01`
+    pub from: String,
+    /// Last day, inclusive
+    pub to: String,
+}
+
+/// Inspections to change at once, all or none
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct BatchRequest {
+    pub ids: Vec<i64>,
+    #[serde(flatten)]
+    pub operation: BatchOperation,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(tag = "operation", rename_all = "camelCase")]
+pub enum BatchOperation {
+    /// 终检 with the final cause, by `inspector` at the current time
+    #[serde(rename_all = "camelCase")]
+    Finalize { cause: RefId, inspector: String },
+    /// Soft-deletes them.
+    Delete,
+    Restore,
+    /// Replaces the cause in effect: the initial one of 初检 inspections, or
+    /// the final one of 终检 ones
+    #[serde(rename_all = "camelCase")]
+    ReassignCause { cause: RefId },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum BatchStatus {
+    Done,
+    NotFound,
+    /// Soft-deleted, so only `restore` applies
+    Deleted,
+    /// Already in that state
+    Unchanged,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(feature = "server", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResult {
+    pub id: i64,
+    pub status: BatchStatus,
+}
+
+#[cfg(feature = "server")]
+mod from_row {
+    use sqlx::{ColumnIndex, Decode, Row, Type};
+
+    use super::{BreakCause, Breakpoint, User};
+
+    impl BreakCause {
+        pub fn from_row_prefixed<'r, R>(row: &'r R, prefix: &str) -> sqlx::Result<Self>
+        where
+            R: Row,
+            for<'a> &'a str: ColumnIndex<R>,
+            i32: Decode<'r, R::Database> + Type<R::Database>,
+            String: Decode<'r, R::Database> + Type<R::Database>,
+        {
+            Ok(Self {
+                id: row.try_get(format!("{prefix}_cause_id").as_str())?,
+                r#type: row.try_get(format!("{prefix}_cause_type").as_str())?,
+                cause: row.try_get(format!("{prefix}_cause_name").as_str())?,
+                enable_state: row.try_get(format!("{prefix}_cause_enable_state").as_str())?,
+            })
+        }
+    }
+
+    impl User {
+        pub fn from_row_prefixed<'r, R>(row: &'r R, prefix: &str) -> sqlx::Result<Self>
+        where
+            R: Row,
+            for<'a> &'a str: ColumnIndex<R>,
+            i32: Decode<'r, R::Database> + Type<R::Database>,
+            String: Decode<'r, R::Database> + Type<R::Database>,
+        {
+            Ok(Self {
+                id: row.try_get(format!("{prefix}_user_id").as_str())?,
+                name: row.try_get(format!("{prefix}_user_name").as_str())?,
+                enable_state: row.try_get(format!("{prefix}_user_enable_state").as_str())?,
+                user_type: row.try_get(format!("{prefix}_user_user_type").as_str())?,
+            })
+        }
+    }
+
+    impl Breakpoint {
+        pub fn from_row_prefixed<'r, R>(row: &'r R, prefix: &str) -> sqlx::Result<Self>
+        where
+            R: Row,
+            for<'a> &'a str: ColumnIndex<R>,
+            i32: Decode<'r, R::Database> + Type<R::Database>,
+            String: Decode<'r, R::Database> + Type<R::Database>,
+        {
+            Ok(Self {
+                id: row.try_get(format!("{prefix}_bp_id").as_str())?,
+                breakpoint: row.try_get(format!("{prefix}_bp_name").as_str())?,
+                enable_state: row.try_get(format!("{prefix}_bp_enable_state").as_str())?,
+            })
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! Typed client of the inspection server
+//!
+//! Every route has an async method here. The `code`/`message` envelope of
+//! the responses is unwrapped: a failure comes back as an [`ApiError`] in
+//! the returned [`anyhow::Error`].
+
+use std::fmt;
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::multipart::{Form, Part};
+use reqwest::{RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+
+pub use czttgd_api::*;
+
+/// API version the client talks to
+const VERSION: &str = "v1";
+
+/// A response with a nonzero `code`
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub code: u32,
+    pub message: Option<String>,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.message {
+            Some(m) => write!(f, "API error {}: {}", self.code, m),
+            None => write!(f, "API error {}", self.code),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// An image to upload with [`Client::upload_attachments`]
+pub struct ImageFile {
+    pub filename: String,
+    pub content: Vec<u8>,
+}
+
+/// A log to upload with [`Client::upload_log`]
+#[derive(Default)]
+pub struct LogFile {
+    /// Ending with `.gz` or `.zst` if compressed
+    pub filename: String,
+    pub content: Vec<u8>,
+    pub device: Option<String>,
+    pub user: Option<String>,
+    pub app_version: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    admin_token: Option<String>,
+}
+
+impl Client {
+    /// `base_url` is like `http://127.0.0.1:8080`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(reqwest::Client::new(), base_url)
+    }
+
+    /// With a preconfigured HTTP client, for timeouts, TLS roots and so on
+    pub fn with_http_client(http: reqwest::Client, base_url: impl Into<String>) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        Self {
+            http,
+            base_url,
+            admin_token: None,
+        }
+    }
+
+    /// For the `/admin` routes and the uploaded logs, as `admin.token` in the
+    /// server config
+    pub fn with_admin_token(mut self, token: impl Into<String>) -> Self {
+        self.admin_token = Some(token.into());
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}{}", self.base_url, VERSION, path)
+    }
+
+    /// With the admin token, if any
+    fn admin(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.admin_token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Sends the request and unwraps the envelope.
+    async fn json<D: DeserializeOwned>(request: RequestBuilder) -> anyhow::Result<D> {
+        let response = request.send().await?;
+        let envelope: ResponseJson<D> = response.json().await?;
+        into_result(envelope)?.ok_or_else(|| anyhow!("Response without data"))
+    }
+
+    /// Like [`Self::json`], for the responses whose `data` is `null`.
+    async fn unit(request: RequestBuilder) -> anyhow::Result<()> {
+        let response = request.send().await?;
+        let envelope: ResponseJson<serde_json::Value> = response.json().await?;
+        into_result(envelope)?;
+        Ok(())
+    }
+
+    /// For the routes responding with raw content, which fall back to the
+    /// envelope on failures
+    async fn raw(request: RequestBuilder) -> anyhow::Result<Response> {
+        let response = request.send().await?.error_for_status()?;
+        let is_json = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|x| x.to_str().ok())
+            .is_some_and(|x| x.starts_with("application/json"));
+        if is_json {
+            let envelope: ResponseJson<serde_json::Value> = response.json().await?;
+            into_result(envelope)?;
+            return Err(anyhow!("Unexpected JSON response"));
+        }
+        Ok(response)
+    }
+
+    async fn bytes(request: RequestBuilder) -> anyhow::Result<Bytes> {
+        Ok(Self::raw(request).await?.bytes().await?)
+    }
+
+    async fn text(request: RequestBuilder) -> anyhow::Result<String> {
+        Ok(Self::raw(request).await?.text().await?)
+    }
+
+    /// `GET /routes`
+    pub async fn routes(&self) -> anyhow::Result<String> {
+        Self::text(self.http.get(self.url("/routes"))).await
+    }
+
+    /// `GET /ping`
+    pub async fn ping(&self, text: &str) -> anyhow::Result<Pong> {
+        Self::json(self.http.get(self.url("/ping")).query(&[("text", text)])).await
+    }
+
+    /// `GET /health`
+    pub async fn health(&self) -> anyhow::Result<String> {
+        Self::json(self.http.get(self.url("/health"))).await
+    }
+
+    /// `GET /ready`
+    pub async fn ready(&self) -> anyhow::Result<String> {
+        Self::json(self.http.get(self.url("/ready"))).await
+    }
+
+    /// `GET /metrics`, in the Prometheus text format
+    pub async fn metrics(&self) -> anyhow::Result<String> {
+        Self::text(self.http.get(self.url("/metrics"))).await
+    }
+
+    /// `GET /openapi.json`
+    pub async fn openapi(&self) -> anyhow::Result<serde_json::Value> {
+        Ok(self.http.get(self.url("/openapi.json")).send().await?.error_for_status()?.json().await?)
+    }
+
+    /// `GET /stage/:stage/devices`; the machine numbers of a stage
+    pub async fn devices(&self, stage: i32) -> anyhow::Result<Vec<i32>> {
+        Self::json(self.http.get(self.url(&format!("/stage/{stage}/devices")))).await
+    }
+
+    /// `GET /machine/:code/history`
+    pub async fn machine_history(
+        &self,
+        code: i32,
+        query: &HistoryQuery,
+    ) -> anyhow::Result<MachineHistory> {
+        Self::json(self.http.get(self.url(&format!("/machine/{code}/history"))).query(query)).await
+    }
+
+    /// `GET /users`
+    pub async fn users(&self) -> anyhow::Result<Vec<User>> {
+        Self::json(self.http.get(self.url("/users"))).await
+    }
+
+    /// `GET /break/causes`
+    pub async fn break_causes(&self) -> anyhow::Result<Vec<BreakCause>> {
+        Self::json(self.http.get(self.url("/break/causes"))).await
+    }
+
+    /// `GET /break/points`
+    pub async fn breakpoints(&self) -> anyhow::Result<Vec<Breakpoint>> {
+        Self::json(self.http.get(self.url("/break/points"))).await
+    }
+
+    /// `POST /inspection`; returns the id of the new inspection
+    pub async fn create_inspection(&self, form: &InspectionForm) -> anyhow::Result<i64> {
+        Self::json(self.http.post(self.url("/inspection")).form(form)).await
+    }
+
+    /// `POST /inspection` with an `Idempotency-Key`; retries with the same
+    /// key return the same id.
+    pub async fn create_inspection_once(
+        &self,
+        key: &str,
+        form: &InspectionForm,
+    ) -> anyhow::Result<i64> {
+        let request = self.http.post(self.url("/inspection"));
+        Self::json(request.header("Idempotency-Key", key).form(form)).await
+    }
+
+    /// `GET /inspection/search`
+    pub async fn search_inspections(
+        &self,
+        query: &SearchQuery,
+    ) -> anyhow::Result<Vec<InspectionSummary>> {
+        Self::json(self.http.get(self.url("/inspection/search")).query(query)).await
+    }
+
+    /// `GET /inspection/:id/details`
+    pub async fn inspection_details(&self, id: i64) -> anyhow::Result<InspectionDetails> {
+        Self::json(self.http.get(self.url(&format!("/inspection/{id}/details")))).await
+    }
+
+    /// `PUT /inspection/:id`
+    pub async fn update_inspection(&self, id: i64, form: &InspectionForm) -> anyhow::Result<()> {
+        Self::unit(self.http.put(self.url(&format!("/inspection/{id}"))).form(form)).await
+    }
+
+    /// `GET /inspection/count`
+    pub async fn inspection_count(&self) -> anyhow::Result<i64> {
+        Self::json(self.http.get(self.url("/inspection/count"))).await
+    }
+
+    /// `POST /inspection/batch`; one result per id, in order
+    pub async fn batch_inspections(
+        &self,
+        request: &BatchRequest,
+    ) -> anyhow::Result<Vec<BatchResult>> {
+        Self::json(self.http.post(self.url("/inspection/batch")).json(request)).await
+    }
+
+    /// `POST /inspection/import` of a `.csv`, `.xlsx` or `.xls` file
+    pub async fn import_inspections(
+        &self,
+        filename: &str,
+        content: Vec<u8>,
+        query: &ImportQuery,
+    ) -> anyhow::Result<ImportReport> {
+        let form = Form::new().part("file", Part::bytes(content).file_name(filename.to_string()));
+        let request = self.http.post(self.url("/inspection/import")).query(query);
+        Self::json(request.multipart(form)).await
+    }
+
+    /// `GET /inspection/:id/report`; a PDF
+    pub async fn inspection_report(&self, id: i64) -> anyhow::Result<Bytes> {
+        Self::bytes(self.http.get(self.url(&format!("/inspection/{id}/report")))).await
+    }
+
+    /// `GET /report/summary`; a PDF
+    pub async fn summary_report(&self, query: &SummaryQuery) -> anyhow::Result<Bytes> {
+        Self::bytes(self.http.get(self.url("/report/summary")).query(query)).await
+    }
+
+    /// `POST /sync/inspections`; one result per item, in order
+    pub async fn push_inspections(&self, items: &[SyncItem]) -> anyhow::Result<Vec<SyncResult>> {
+        Self::json(self.http.post(self.url("/sync/inspections")).json(items)).await
+    }
+
+    /// `GET /sync/changes`
+    pub async fn inspection_changes(&self, query: &ChangesQuery) -> anyhow::Result<Changes> {
+        Self::json(self.http.get(self.url("/sync/changes")).query(query)).await
+    }
+
+    /// `GET /inspection/updatecounter`; changes on every insertion or update
+    pub async fn update_counter(&self) -> anyhow::Result<u32> {
+        let response = self.http.get(self.url("/inspection/updatecounter")).send().await?;
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    /// `POST /inspection/:id/attachments`
+    pub async fn upload_attachments(
+        &self,
+        inspection_id: i64,
+        files: Vec<ImageFile>,
+    ) -> anyhow::Result<Vec<Attachment>> {
+        let mut form = Form::new();
+        for f in files {
+            form = form.part("file", Part::bytes(f.content).file_name(f.filename));
+        }
+        let url = self.url(&format!("/inspection/{inspection_id}/attachments"));
+        Self::json(self.http.post(url).multipart(form)).await
+    }
+
+    /// `GET /inspection/:id/attachments`
+    pub async fn attachments(&self, inspection_id: i64) -> anyhow::Result<Vec<Attachment>> {
+        Self::json(self.http.get(self.url(&format!("/inspection/{inspection_id}/attachments")))).await
+    }
+
+    /// `GET /attachment/:id`; the original image
+    pub async fn attachment(&self, id: i64) -> anyhow::Result<Bytes> {
+        Self::bytes(self.http.get(self.url(&format!("/attachment/{id}")))).await
+    }
+
+    /// `GET /attachment/:id/thumbnail`; a JPEG
+    pub async fn attachment_thumbnail(&self, id: i64) -> anyhow::Result<Bytes> {
+        Self::bytes(self.http.get(self.url(&format!("/attachment/{id}/thumbnail")))).await
+    }
+
+    /// `DELETE /attachment/:id`
+    pub async fn delete_attachment(&self, id: i64) -> anyhow::Result<()> {
+        Self::unit(self.http.delete(self.url(&format!("/attachment/{id}")))).await
+    }
+
+    /// `POST /log`; returns the id of the upload
+    pub async fn upload_log(&self, log: LogFile) -> anyhow::Result<String> {
+        let mut form = Form::new();
+        for (name, value) in [
+            ("device", log.device),
+            ("user", log.user),
+            ("appVersion", log.app_version),
+        ] {
+            if let Some(v) = value {
+                form = form.text(name, v);
+            }
+        }
+        form = form.part("file", Part::bytes(log.content).file_name(log.filename));
+        Self::json(self.http.post(self.url("/log")).multipart(form)).await
+    }
+
+    /// `GET /logs`, the newest first
+    pub async fn logs(&self, query: &ListQuery) -> anyhow::Result<Vec<LogMetadata>> {
+        Self::json(self.admin(self.http.get(self.url("/logs")).query(query))).await
+    }
+
+    /// `GET /log/:id`
+    pub async fn download_log(&self, id: &str) -> anyhow::Result<Bytes> {
+        Self::bytes(self.admin(self.http.get(self.url(&format!("/log/{id}"))))).await
+    }
+
+    /// `DELETE /log/:id`
+    pub async fn delete_log(&self, id: &str) -> anyhow::Result<()> {
+        Self::unit(self.admin(self.http.delete(self.url(&format!("/log/{id}"))))).await
+    }
+
+    /// `POST /admin/reload`
+    pub async fn reload_config(&self) -> anyhow::Result<ReloadReport> {
+        Self::json(self.admin(self.http.post(self.url("/admin/reload")))).await
+    }
+}
+
+/// `data` on success, or an [`ApiError`]
+fn into_result<D>(envelope: ResponseJson<D>) -> Result<Option<D>, ApiError> {
+    match envelope.code {
+        0 => Ok(envelope.data),
+        code => Err(ApiError {
+            code,
+            message: envelope.message,
+        }),
+    }
+}
@@ -0,0 +1,16 @@
+-- Photos attached to inspections. The image and its thumbnail are kept in
+-- the attachment store; only their keys are stored here.
+CREATE TABLE inspection_attachment
+(
+    id            BIGINT AUTO_INCREMENT PRIMARY KEY,
+    inspection_id BIGINT       NOT NULL,
+    filename      VARCHAR(255) NULL,
+    content_type  VARCHAR(64)  NOT NULL,
+    size          BIGINT       NOT NULL,
+    width         INT          NOT NULL,
+    height        INT          NOT NULL,
+    object_key    VARCHAR(255) NOT NULL,
+    thumbnail_key VARCHAR(255) NOT NULL,
+    created_at    DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_inspection_attachment_inspection (inspection_id)
+);
@@ -0,0 +1,39 @@
+-- Versions for the offline sync. Every change of an inspection made through
+-- this server takes the next `sync_version`; its row is locked until the
+-- change commits, so versions become visible in order.
+CREATE TABLE sync_version
+(
+    version BIGINT NOT NULL
+);
+
+-- The latest version of each inspection
+CREATE TABLE inspection_version
+(
+    inspection_id BIGINT PRIMARY KEY,
+    version       BIGINT NOT NULL,
+    UNIQUE INDEX idx_inspection_version_version (version)
+);
+
+-- Inspections pushed by the tablets, by the UUIDs they gave them
+CREATE TABLE inspection_sync
+(
+    client_id     CHAR(36) PRIMARY KEY,
+    inspection_id BIGINT   NOT NULL,
+    -- the last form accepted from the client, as JSON
+    form          TEXT     NOT NULL,
+    -- the version that form got
+    version       BIGINT   NOT NULL,
+    INDEX idx_inspection_sync_inspection (inspection_id)
+);
+
+-- existing inspections, the oldest first; counted with a variable, as
+-- MySQL 5.7 has no window functions
+INSERT INTO inspection_version (inspection_id, version)
+SELECT i.id, @version := @version + 1
+FROM tt_inspect i,
+     (SELECT @version := 0) init
+ORDER BY i.id;
+
+INSERT INTO sync_version (version)
+SELECT COUNT(*)
+FROM tt_inspect;
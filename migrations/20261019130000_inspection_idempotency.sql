@@ -0,0 +1,10 @@
+-- `Idempotency-Key`s of `POST /inspection`, with the inspections they
+-- created. Expired ones are pruned on insertion.
+CREATE TABLE inspection_idempotency
+(
+    idempotency_key VARCHAR(255) PRIMARY KEY,
+    inspection_id   BIGINT       NOT NULL,
+    -- Unix seconds
+    created_at      BIGINT       NOT NULL,
+    INDEX idx_inspection_idempotency_created_at (created_at)
+);
@@ -0,0 +1,70 @@
+-- The parts of the `breakInfo` schema used by the server. On MySQL these
+-- tables belong to the customer and already exist.
+
+CREATE TABLE tt_user
+(
+    userid      INTEGER PRIMARY KEY,
+    name        VARCHAR(64) NOT NULL,
+    usertype    VARCHAR(8)  NOT NULL,
+    enablestate INT         NOT NULL
+);
+
+CREATE TABLE tt_machine
+(
+    machinenumber INTEGER PRIMARY KEY,
+    stage         INT NOT NULL
+);
+
+CREATE TABLE tt_breakreason
+(
+    breakreasonid INTEGER PRIMARY KEY,
+    reasontype    VARCHAR(64)  NULL,
+    breakreason   VARCHAR(255) NULL,
+    enablestate   INT          NOT NULL
+);
+
+CREATE TABLE tt_breakpoint
+(
+    breakpointid INTEGER PRIMARY KEY,
+    breakpoint   VARCHAR(255) NULL,
+    enablestate  INT          NOT NULL
+);
+
+CREATE TABLE tt_number
+(
+    serialNum INT NOT NULL
+);
+
+INSERT INTO tt_number (serialNum)
+VALUES (1);
+
+CREATE TABLE tt_inspect
+(
+    id             INTEGER PRIMARY KEY,
+    creator        INT         NOT NULL,
+    devicecode     INT         NOT NULL,
+    devicecategory VARCHAR(64) NOT NULL,
+    creationtime   VARCHAR(32) NOT NULL,
+    spec           VARCHAR(64) NULL,
+    wirenum        INT         NULL,
+    wiretype       VARCHAR(64) NULL,
+    breakspec      VARCHAR(64) NOT NULL,
+    twbatchcode    VARCHAR(64) NULL,
+    trbatchcode    VARCHAR(64) NULL,
+    dlwarehouse    VARCHAR(64) NULL,
+    tgproducttime  VARCHAR(32) NULL,
+    breakflag      CHAR(1)     NOT NULL,
+    breakpointa    INT         NULL,
+    -- `DECIMAL(10, 2)` on MySQL; kept as text with two decimal places, as
+    -- SQLite has no exact decimals
+    breakpointb    TEXT        NULL,
+    memo           VARCHAR(255) NULL,
+    breakreasona   INT         NULL,
+    breakreasonb   INT         NULL,
+    billflag       INT         NOT NULL DEFAULT 0,
+    inspector      VARCHAR(64) NULL,
+    inspecttime    VARCHAR(32) NULL,
+    deleteflag     INT         NOT NULL DEFAULT 0
+);
+
+CREATE INDEX idx_tt_inspect_devicecode ON tt_inspect (devicecode);
@@ -0,0 +1,18 @@
+-- Photos attached to inspections. The image and its thumbnail are kept in
+-- the attachment store; only their keys are stored here.
+CREATE TABLE inspection_attachment
+(
+    id            INTEGER PRIMARY KEY AUTOINCREMENT,
+    inspection_id BIGINT       NOT NULL,
+    filename      VARCHAR(255) NULL,
+    content_type  VARCHAR(64)  NOT NULL,
+    size          BIGINT       NOT NULL,
+    width         INT          NOT NULL,
+    height        INT          NOT NULL,
+    object_key    VARCHAR(255) NOT NULL,
+    thumbnail_key VARCHAR(255) NOT NULL,
+    -- local time like MySQL's `CURRENT_TIMESTAMP`, as `YYYY-MM-DD HH:MM:SS`
+    created_at    TEXT         NOT NULL DEFAULT (datetime('now', 'localtime'))
+);
+
+CREATE INDEX idx_inspection_attachment_inspection ON inspection_attachment (inspection_id);
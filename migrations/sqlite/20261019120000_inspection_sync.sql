@@ -0,0 +1,37 @@
+-- Versions for the offline sync. Every change of an inspection made through
+-- this server takes the next `sync_version` in its write transaction.
+CREATE TABLE sync_version
+(
+    version BIGINT NOT NULL
+);
+
+-- The latest version of each inspection
+CREATE TABLE inspection_version
+(
+    inspection_id BIGINT PRIMARY KEY,
+    version       BIGINT NOT NULL
+);
+
+CREATE UNIQUE INDEX idx_inspection_version_version ON inspection_version (version);
+
+-- Inspections pushed by the tablets, by the UUIDs they gave them
+CREATE TABLE inspection_sync
+(
+    client_id     CHAR(36) PRIMARY KEY,
+    inspection_id BIGINT   NOT NULL,
+    -- the last form accepted from the client, as JSON
+    form          TEXT     NOT NULL,
+    -- the version that form got
+    version       BIGINT   NOT NULL
+);
+
+CREATE INDEX idx_inspection_sync_inspection ON inspection_sync (inspection_id);
+
+-- existing inspections, the oldest first
+INSERT INTO inspection_version (inspection_id, version)
+SELECT id, ROW_NUMBER() OVER (ORDER BY id)
+FROM tt_inspect;
+
+INSERT INTO sync_version (version)
+SELECT COUNT(*)
+FROM tt_inspect;
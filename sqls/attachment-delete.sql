@@ -0,0 +1,3 @@
+DELETE
+FROM inspection_attachment
+WHERE id = ?
\ No newline at end of file
@@ -0,0 +1,12 @@
+SELECT id,
+       inspection_id,
+       filename,
+       content_type,
+       size,
+       width,
+       height,
+       object_key,
+       thumbnail_key,
+       DATE_FORMAT(created_at, '%Y-%m-%d %H:%i:%s') as creation_time
+FROM inspection_attachment
+ORDER BY id
\ No newline at end of file
@@ -0,0 +1,10 @@
+INSERT INTO inspection_attachment
+(inspection_id,
+ filename,
+ content_type,
+ size,
+ width,
+ height,
+ object_key,
+ thumbnail_key)
+    VALUE (?, ?, ?, ?, ?, ?, ?, ?)
\ No newline at end of file
@@ -0,0 +1,12 @@
+INSERT INTO inspection_attachment
+(id,
+ inspection_id,
+ filename,
+ content_type,
+ size,
+ width,
+ height,
+ object_key,
+ thumbnail_key,
+ created_at)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
\ No newline at end of file
@@ -0,0 +1,2 @@
+INSERT INTO tt_breakreason (breakreasonid, reasontype, breakreason, enablestate)
+    VALUES (?, ?, ?, ?)
\ No newline at end of file
@@ -0,0 +1,4 @@
+UPDATE tt_breakreason
+SET enablestate = ?
+WHERE breakreasonid = ?
+  AND enablestate <> ?
\ No newline at end of file
@@ -0,0 +1,2 @@
+INSERT INTO tt_breakpoint (breakpointid, breakpoint, enablestate)
+    VALUES (?, ?, ?)
\ No newline at end of file
@@ -0,0 +1,4 @@
+UPDATE tt_breakpoint
+SET enablestate = ?
+WHERE breakpointid = ?
+  AND enablestate <> ?
\ No newline at end of file
@@ -0,0 +1,2 @@
+INSERT INTO tt_number (serialNum)
+    VALUES (?)
\ No newline at end of file
@@ -0,0 +1,6 @@
+SELECT (SELECT COUNT(*) FROM tt_inspect)
+           + (SELECT COUNT(*) FROM tt_user)
+           + (SELECT COUNT(*) FROM tt_machine)
+           + (SELECT COUNT(*) FROM tt_breakreason)
+           + (SELECT COUNT(*) FROM tt_breakpoint)
+           + (SELECT COUNT(*) FROM inspection_attachment) as c
\ No newline at end of file
@@ -0,0 +1,3 @@
+SELECT devicecode, billflag, deleteflag, breakreasona, breakreasonb
+FROM tt_inspect
+WHERE id = ?
\ No newline at end of file
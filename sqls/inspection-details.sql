@@ -16,7 +16,7 @@ SELECT i.devicecode       as device_code,
        i.dlwarehouse      as warehouse,
        i.tgproducttime    as product_time,
        i.breakflag = '1'  as break_flag,
-       i.breakpointb      as breakpoint_b,
+       CAST(i.breakpointb AS CHAR) as breakpoint_b,
        i.breakpointa      as breakpoint_a,
        bp_a.breakpointid  as bp_a_bp_id,
        bp_a.breakpoint    as bp_a_bp_name,
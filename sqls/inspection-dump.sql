@@ -0,0 +1,25 @@
+SELECT id,
+       creator,
+       devicecode,
+       devicecategory,
+       creationtime,
+       spec,
+       wirenum,
+       wiretype,
+       breakspec,
+       twbatchcode,
+       trbatchcode,
+       dlwarehouse,
+       tgproducttime,
+       breakflag,
+       breakpointa,
+       CAST(breakpointb AS CHAR) as breakpointb,
+       memo,
+       breakreasona,
+       breakreasonb,
+       billflag,
+       inspector,
+       inspecttime,
+       deleteflag
+FROM tt_inspect
+ORDER BY id
\ No newline at end of file
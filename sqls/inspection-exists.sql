@@ -0,0 +1,3 @@
+SELECT COUNT(*) as c
+FROM tt_inspect
+WHERE id = ?
\ No newline at end of file
@@ -0,0 +1,31 @@
+SELECT i.id,
+       i.devicecode      as device_code,
+       i.devicecategory  as device_category,
+       i.creationtime    as creation_time,
+       u.name            as creator,
+       i.billflag        as inspection_flag,
+       i.spec            as product_spec,
+       i.wirenum         as wire_num,
+       i.wiretype        as wire_type,
+       i.breakspec       as break_spec,
+       i.twbatchcode     as wire_batch_code,
+       i.trbatchcode     as stick_batch_code,
+       i.dlwarehouse     as warehouse,
+       i.tgproducttime   as product_time,
+       i.breakflag = '1' as break_flag,
+       CAST(i.breakpointb AS CHAR) as breakpoint_b,
+       bp_a.breakpoint   as breakpoint_a,
+       br_a.breakreason  as break_cause_a,
+       br_b.breakreason  as break_cause_b,
+       i.memo            as comments,
+       i.inspector,
+       i.inspecttime     as inspection_time
+FROM tt_inspect i
+         LEFT JOIN tt_user u ON i.creator = u.userid
+         LEFT JOIN tt_breakpoint bp_a ON i.breakpointa = bp_a.breakpointid
+         LEFT JOIN tt_breakreason br_a ON i.breakreasona = br_a.breakreasonid
+         LEFT JOIN tt_breakreason br_b ON i.breakreasonb = br_b.breakreasonid
+WHERE i.deleteflag = 0
+  AND i.creationtime >= ?
+  AND i.creationtime < DATE_FORMAT(DATE_ADD(?, INTERVAL 1 DAY), '%Y-%m-%d')
+ORDER BY i.creationtime
\ No newline at end of file
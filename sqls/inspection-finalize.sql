@@ -0,0 +1,6 @@
+UPDATE tt_inspect
+SET breakreasonb = ?,
+    inspector    = ?,
+    inspecttime  = ?,
+    billflag     = 1
+WHERE id = ?
\ No newline at end of file
@@ -0,0 +1,3 @@
+SELECT MAX(id) as id
+FROM tt_inspect
+WHERE id BETWEEN ? AND ?
\ No newline at end of file
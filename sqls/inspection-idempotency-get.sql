@@ -0,0 +1,4 @@
+SELECT inspection_id
+FROM inspection_idempotency
+WHERE idempotency_key = ?
+  AND created_at > ?
\ No newline at end of file
@@ -0,0 +1,2 @@
+INSERT INTO inspection_idempotency (idempotency_key, inspection_id, created_at)
+VALUES (?, ?, ?)
\ No newline at end of file
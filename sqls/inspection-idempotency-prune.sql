@@ -0,0 +1,3 @@
+DELETE
+FROM inspection_idempotency
+WHERE created_at <= ?
\ No newline at end of file
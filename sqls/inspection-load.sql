@@ -0,0 +1,25 @@
+INSERT INTO tt_inspect
+(id,
+ creator,
+ devicecode,
+ devicecategory,
+ creationtime,
+ spec,
+ wirenum,
+ wiretype,
+ breakspec,
+ twbatchcode,
+ trbatchcode,
+ dlwarehouse,
+ tgproducttime,
+ breakflag,
+ breakpointa,
+ breakpointb,
+ memo,
+ breakreasona,
+ breakreasonb,
+ billflag,
+ inspector,
+ inspecttime,
+ deleteflag)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
\ No newline at end of file
@@ -0,0 +1,3 @@
+UPDATE tt_inspect
+SET breakreasona = ?
+WHERE id = ?
\ No newline at end of file
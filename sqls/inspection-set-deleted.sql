@@ -0,0 +1,4 @@
+UPDATE tt_inspect
+SET deleteflag = ?
+WHERE id = ?
+  AND deleteflag <> ?
\ No newline at end of file
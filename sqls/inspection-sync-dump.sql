@@ -0,0 +1,3 @@
+SELECT client_id, inspection_id, form, version
+FROM inspection_sync
+ORDER BY version
\ No newline at end of file
@@ -0,0 +1,2 @@
+INSERT INTO inspection_sync (client_id, inspection_id, form, version)
+VALUES (?, ?, ?, ?)
\ No newline at end of file
@@ -0,0 +1,4 @@
+UPDATE inspection_sync
+SET form    = ?,
+    version = ?
+WHERE client_id = ?
\ No newline at end of file
@@ -0,0 +1,3 @@
+SELECT inspection_id, version
+FROM inspection_version
+ORDER BY version
\ No newline at end of file
@@ -0,0 +1,3 @@
+INSERT INTO inspection_version (inspection_id, version)
+VALUES (?, ?)
+ON DUPLICATE KEY UPDATE version = VALUES(version)
\ No newline at end of file
@@ -0,0 +1,3 @@
+SELECT version
+FROM inspection_version
+WHERE inspection_id = ?
\ No newline at end of file
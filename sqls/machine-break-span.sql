@@ -0,0 +1,8 @@
+SELECT COUNT(*)                                                       as c,
+       TIMESTAMPDIFF(SECOND, MIN(i.creationtime), MAX(i.creationtime)) as span_secs
+FROM tt_inspect i
+         INNER JOIN tt_machine m ON i.devicecode = m.machinenumber
+WHERE i.devicecode = ?
+  AND i.deleteflag = 0
+  AND (? IS NULL OR i.creationtime >= ?)
+  AND (? IS NULL OR i.creationtime <= ?)
\ No newline at end of file
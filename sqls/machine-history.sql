@@ -0,0 +1,31 @@
+SELECT i.id,
+       i.creationtime     as creation_time,
+       i.billflag         as inspection_flag,
+       i.breakflag = '1'  as break_flag,
+       i.breakspec        as break_spec,
+       CAST(i.breakpointb AS CHAR) as breakpoint_b,
+       i.breakpointa      as breakpoint_a,
+       bp_a.breakpointid  as bp_a_bp_id,
+       bp_a.breakpoint    as bp_a_bp_name,
+       bp_a.enablestate   as bp_a_bp_enable_state,
+       i.breakreasona     as break_cause_a,
+       br_a.breakreasonid as br_a_cause_id,
+       br_a.reasontype    as br_a_cause_type,
+       br_a.breakreason   as br_a_cause_name,
+       br_a.enablestate   as br_a_cause_enable_state,
+       i.breakreasonb     as break_cause_b,
+       br_b.breakreasonid as br_b_cause_id,
+       br_b.reasontype    as br_b_cause_type,
+       br_b.breakreason   as br_b_cause_name,
+       br_b.enablestate   as br_b_cause_enable_state
+FROM tt_inspect i
+         INNER JOIN tt_machine m ON i.devicecode = m.machinenumber
+         LEFT JOIN tt_breakpoint bp_a ON i.breakpointa = bp_a.breakpointid
+         LEFT JOIN tt_breakreason br_a ON i.breakreasona = br_a.breakreasonid
+         LEFT JOIN tt_breakreason br_b ON i.breakreasonb = br_b.breakreasonid
+WHERE i.devicecode = ?
+  AND i.deleteflag = 0
+  AND (? IS NULL OR i.creationtime >= ?)
+  AND (? IS NULL OR i.creationtime <= ?)
+ORDER BY i.creationtime DESC
+LIMIT ?
\ No newline at end of file
@@ -0,0 +1,2 @@
+INSERT INTO tt_machine (machinenumber, stage)
+    VALUES (?, ?)
\ No newline at end of file
@@ -0,0 +1,14 @@
+SELECT bp.breakpointid as bp_id,
+       bp.breakpoint   as bp_name,
+       bp.enablestate  as bp_enable_state,
+       COUNT(*)        as count
+FROM tt_inspect i
+         INNER JOIN tt_machine m ON i.devicecode = m.machinenumber
+         INNER JOIN tt_breakpoint bp ON i.breakpointa = bp.breakpointid
+WHERE i.devicecode = ?
+  AND i.deleteflag = 0
+  AND (? IS NULL OR i.creationtime >= ?)
+  AND (? IS NULL OR i.creationtime <= ?)
+GROUP BY bp.breakpointid, bp.breakpoint, bp.enablestate
+ORDER BY count DESC
+LIMIT ?
\ No newline at end of file
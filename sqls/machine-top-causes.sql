@@ -0,0 +1,16 @@
+-- The final cause takes precedence over the initial one once an inspection is finalized.
+SELECT br.breakreasonid as cause_id,
+       br.reasontype    as cause_type,
+       br.breakreason   as cause_name,
+       br.enablestate   as cause_enable_state,
+       COUNT(*)         as count
+FROM tt_inspect i
+         INNER JOIN tt_machine m ON i.devicecode = m.machinenumber
+         INNER JOIN tt_breakreason br ON COALESCE(i.breakreasonb, i.breakreasona) = br.breakreasonid
+WHERE i.devicecode = ?
+  AND i.deleteflag = 0
+  AND (? IS NULL OR i.creationtime >= ?)
+  AND (? IS NULL OR i.creationtime <= ?)
+GROUP BY br.breakreasonid, br.reasontype, br.breakreason, br.enablestate
+ORDER BY count DESC
+LIMIT ?
\ No newline at end of file
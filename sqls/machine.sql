@@ -0,0 +1,4 @@
+SELECT machinenumber as device_code,
+       stage
+FROM tt_machine
+WHERE machinenumber = ?
\ No newline at end of file
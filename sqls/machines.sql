@@ -0,0 +1,3 @@
+SELECT machinenumber, stage
+FROM tt_machine
+ORDER BY machinenumber
\ No newline at end of file
@@ -0,0 +1,16 @@
+SELECT a.id,
+       a.inspection_id,
+       a.filename,
+       a.content_type,
+       a.size,
+       a.width,
+       a.height,
+       a.object_key,
+       a.thumbnail_key,
+       a.created_at as creation_time
+FROM inspection_attachment a
+         JOIN inspection_version v
+              ON v.inspection_id = a.inspection_id
+WHERE v.version > ?
+  AND v.version <= ?
+ORDER BY a.id
\ No newline at end of file
@@ -0,0 +1,12 @@
+SELECT id,
+       inspection_id,
+       filename,
+       content_type,
+       size,
+       width,
+       height,
+       object_key,
+       thumbnail_key,
+       created_at as creation_time
+FROM inspection_attachment
+WHERE id = ?
\ No newline at end of file
@@ -0,0 +1,13 @@
+SELECT id,
+       inspection_id,
+       filename,
+       content_type,
+       size,
+       width,
+       height,
+       object_key,
+       thumbnail_key,
+       created_at as creation_time
+FROM inspection_attachment
+WHERE inspection_id = ?
+ORDER BY id
\ No newline at end of file
@@ -0,0 +1 @@
+UPDATE tt_number SET serialNum = ?
\ No newline at end of file
@@ -0,0 +1,21 @@
+INSERT INTO tt_inspect
+(creator,
+ devicecode,
+ creationtime,
+ spec,
+ wirenum,
+ wiretype,
+ breakspec,
+ twbatchcode,
+ trbatchcode,
+ dlwarehouse,
+ tgproducttime,
+ breakflag,
+ breakpointa,
+ breakpointb,
+ memo,
+ devicecategory,
+ breakreasona,
+ billflag,
+ id)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0, ?)
\ No newline at end of file
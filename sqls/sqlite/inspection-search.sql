@@ -0,0 +1,44 @@
+SELECT i.breakreasona     as br_a,
+       i.breakreasonb     as br_b,
+       br_a.breakreasonid as br_a_cause_id,
+       br_a.reasontype    as br_a_cause_type,
+       br_a.breakreason   as br_a_cause_name,
+       br_a.enablestate   as br_a_cause_enable_state,
+       br_b.breakreasonid as br_b_cause_id,
+       br_b.reasontype    as br_b_cause_type,
+       br_b.breakreason   as br_b_cause_name,
+       br_b.enablestate   as br_b_cause_enable_state,
+       i.spec             as product_spec,
+       i.breakflag = '1'  as break_flag,
+       i.breakspec        as break_spec,
+       i.creator,
+       u.userid           as user_id,
+       u.name             as user_name,
+       u.usertype         as user_user_type,
+       u.enablestate      as user_enable_state,
+       i.creationtime     as creation_time,
+       i.billflag         as inspection_flag,
+       i.devicecode       as device_code,
+       i.id
+FROM tt_inspect i
+         INNER JOIN tt_machine m ON i.devicecode = m.machinenumber
+         LEFT JOIN tt_user u ON i.creator = u.userid
+         LEFT JOIN tt_breakreason br_a ON i.breakreasona = br_a.breakreasonid
+         LEFT JOIN tt_breakreason br_b ON i.breakreasona = br_b.breakreasonid
+WHERE m.stage = ?
+  AND i.deleteflag = 0
+  AND (
+    u.name LIKE '%' || ? || '%'
+        OR br_a.breakreason LIKE '%' || ? || '%'
+        OR br_b.breakreason LIKE '%' || ? || '%'
+        OR i.spec LIKE '%' || ? || '%'
+        OR i.creationtime LIKE '%' || strftime('%Y-%m-%d', ?) || '%'
+        OR i.creationtime LIKE '%' || ? || '%'
+        OR REPLACE(i.creationtime, '-', '.') LIKE '%' || ? || '%'
+        OR REPLACE(i.creationtime, '-0', '.') LIKE '%' || ? || '%'
+        OR devicecode || '号机台' LIKE '%' || ? || '%'
+        OR i.memo LIKE '%' || ? || '%'
+        OR i.wiretype LIKE '%' || ? || '%'
+    )
+ORDER BY id DESC
+LIMIT ? OFFSET ?
\ No newline at end of file
@@ -0,0 +1,3 @@
+INSERT INTO inspection_version (inspection_id, version)
+VALUES (?, ?)
+ON CONFLICT (inspection_id) DO UPDATE SET version = excluded.version
\ No newline at end of file
@@ -0,0 +1,9 @@
+SELECT COUNT(*)                                       as c,
+       CAST(strftime('%s', MAX(i.creationtime)) AS INTEGER)
+           - CAST(strftime('%s', MIN(i.creationtime)) AS INTEGER) as span_secs
+FROM tt_inspect i
+         INNER JOIN tt_machine m ON i.devicecode = m.machinenumber
+WHERE i.devicecode = ?
+  AND i.deleteflag = 0
+  AND (? IS NULL OR i.creationtime >= ?)
+  AND (? IS NULL OR i.creationtime <= ?)
\ No newline at end of file
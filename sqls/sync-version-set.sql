@@ -0,0 +1,2 @@
+-- noinspection SqlWithoutWhere
+UPDATE sync_version SET version = ?
\ No newline at end of file
@@ -0,0 +1,2 @@
+SELECT version
+FROM sync_version
\ No newline at end of file
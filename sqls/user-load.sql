@@ -0,0 +1,2 @@
+INSERT INTO tt_user (userid, name, usertype, enablestate)
+    VALUES (?, ?, ?, ?)
\ No newline at end of file
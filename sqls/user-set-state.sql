@@ -0,0 +1,4 @@
+UPDATE tt_user
+SET enablestate = ?
+WHERE userid = ?
+  AND enablestate <> ?
\ No newline at end of file
@@ -0,0 +1,59 @@
+//! Storage and thumbnails of inspection photos
+
+use std::fs::create_dir_all;
+use std::io::Cursor;
+
+use anyhow::anyhow;
+use image::{ImageFormat, ImageReader};
+use object_store::local::LocalFileSystem;
+use object_store::prefix::PrefixStore;
+use object_store::ObjectStore;
+use url::Url;
+
+use crate::config;
+
+pub fn open_store(config: &config::Attachment) -> anyhow::Result<Box<dyn ObjectStore>> {
+    match &config.store_url {
+        Some(url) => {
+            let (store, prefix) =
+                object_store::parse_url_opts(&Url::parse(url)?, &config.store_options)?;
+            Ok(Box::new(PrefixStore::new(store, prefix)))
+        }
+        None => {
+            create_dir_all(&config.dir)?;
+            Ok(Box::new(LocalFileSystem::new_with_prefix(&config.dir)?))
+        }
+    }
+}
+
+/// A decoded image ready to be stored
+pub struct ProcessedImage {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    /// JPEG
+    pub thumbnail: Vec<u8>,
+}
+
+/// Checks that `data` is a supported image, and makes its thumbnail.
+///
+/// CPU-bound; run it with `spawn_blocking`.
+pub fn process_image(data: &[u8], thumbnail_size: u32) -> anyhow::Result<ProcessedImage> {
+    let reader = ImageReader::new(Cursor::new(data)).with_guessed_format()?;
+    let format = reader
+        .format()
+        .filter(|f| matches!(f, ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP))
+        .ok_or_else(|| anyhow!("Unsupported image format"))?;
+    let image = reader.decode()?;
+
+    let thumbnail = image.thumbnail(thumbnail_size, thumbnail_size).into_rgb8();
+    let mut encoded = Vec::new();
+    thumbnail.write_to(&mut Cursor::new(&mut encoded), ImageFormat::Jpeg)?;
+
+    Ok(ProcessedImage {
+        format,
+        width: image.width(),
+        height: image.height(),
+        thumbnail: encoded,
+    })
+}
@@ -0,0 +1,191 @@
+//! Admin subcommands for operators, in place of a MySQL shell
+
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::anyhow;
+
+use crate::config::{read_config_file, Backend, Config};
+use crate::db::Database;
+use crate::repository::Repositories;
+use crate::{attachment, import, summary, Command, RefKind};
+
+/// `enablestate` of enabled users, break causes and breakpoints
+const ENABLED: i32 = 1;
+/// `enablestate` of disabled ones; also what the placeholder inspector
+/// in `inspection-details.sql` has
+const DISABLED: i32 = 2;
+
+/// Runs the command; the config has been loaded successfully by now.
+pub async fn run(command: Command, config: &Config) -> anyhow::Result<()> {
+    // fail fast instead of retrying like the server does
+    let db = Database::connect_once(config).await?;
+    let repositories = db.repositories();
+    let result = match command {
+        Command::Serial { value, reset } => {
+            serial(&repositories, if reset { Some(1) } else { value }).await
+        }
+        Command::Inspection { id } => show_inspection(&repositories, id).await,
+        Command::Delete { id } => set_deleted(&repositories, id, true).await,
+        Command::Restore { id } => set_deleted(&repositories, id, false).await,
+        Command::Enable { kind, id } => set_state(&repositories, kind, id, ENABLED).await,
+        Command::Disable { kind, id } => set_state(&repositories, kind, id, DISABLED).await,
+        Command::Migrate => {
+            let pending = db.pending_migrations().await?;
+            db.migrate().await?;
+            println!("Applied {} migration(s)", pending.len());
+            Ok(())
+        }
+        Command::Export { from, to, output } => {
+            let output: Box<dyn Write> = match output {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout()),
+            };
+            export(&repositories, &from, &to, output).await
+        }
+        Command::Import { file, dry_run, map } => {
+            import(&repositories, &file, dry_run, map.as_deref()).await
+        }
+        Command::Check => check(config, &db).await,
+        Command::Copy { config } => copy(&repositories, &config).await,
+    };
+    db.close().await;
+    result
+}
+
+async fn serial(repositories: &Repositories, value: Option<i32>) -> anyhow::Result<()> {
+    let current = repositories.inspections.serial().await?;
+    match value {
+        Some(v) => {
+            if !(1..1000).contains(&v) {
+                return Err(anyhow!("The serial must be within 1..=999"));
+            }
+            repositories.inspections.set_serial(v).await?;
+            println!("Serial: {} -> {}", current, v);
+        }
+        None => println!("Serial: {}", current),
+    }
+    Ok(())
+}
+
+async fn show_inspection(repositories: &Repositories, id: i64) -> anyhow::Result<()> {
+    let Some(details) = repositories.inspections.details(id).await? else {
+        return Err(anyhow!("No such inspection: {}", id));
+    };
+    println!("{}", serde_json::to_string_pretty(&details)?);
+    Ok(())
+}
+
+async fn set_deleted(repositories: &Repositories, id: i64, deleted: bool) -> anyhow::Result<()> {
+    if !repositories.inspections.set_deleted(id, deleted).await? {
+        let state = if deleted { "deleted" } else { "restored" };
+        return Err(anyhow!("No such inspection, or already {}: {}", state, id));
+    }
+    println!("Done");
+    Ok(())
+}
+
+async fn set_state(
+    repositories: &Repositories,
+    kind: RefKind,
+    id: i32,
+    state: i32,
+) -> anyhow::Result<()> {
+    if !repositories.master_data.set_state(kind, id, state).await? {
+        return Err(anyhow!("No such {:?}, or unchanged: {}", kind, id));
+    }
+    println!("Done");
+    Ok(())
+}
+
+async fn export(
+    repositories: &Repositories,
+    from: &str,
+    to: &str,
+    mut output: impl Write,
+) -> anyhow::Result<()> {
+    // BOM, so Excel detects UTF-8
+    output.write_all(b"\xEF\xBB\xBF")?;
+    let mut writer = csv::Writer::from_writer(output);
+    let rows = repositories.inspections.export(from, to).await?;
+    for row in &rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    eprintln!("Exported {} inspection(s)", rows.len());
+    Ok(())
+}
+
+async fn import(
+    repositories: &Repositories,
+    file: &Path,
+    dry_run: bool,
+    map: Option<&str>,
+) -> anyhow::Result<()> {
+    let map = import::parse_map(map.unwrap_or_default())?;
+    let content = std::fs::read(file)?;
+    let report = import::import(
+        content,
+        &map,
+        dry_run,
+        &*repositories.inspections,
+        &*repositories.master_data,
+    )
+    .await?;
+    for e in &report.errors {
+        match &e.column {
+            Some(column) => eprintln!("Row {}, {}: {}", e.row, column, e.message),
+            None => eprintln!("Row {}: {}", e.row, e.message),
+        }
+    }
+    if !report.errors.is_empty() {
+        return Err(anyhow!("Nothing imported: {} error(s)", report.errors.len()));
+    }
+    match dry_run {
+        true => println!("Would import {} of {} row(s)", report.imported, report.rows),
+        false => println!("Imported {} inspection(s)", report.imported),
+    }
+    Ok(())
+}
+
+/// Everything the server needs on startup except for binding the ports
+async fn check(config: &Config, db: &Database) -> anyhow::Result<()> {
+    db.ping().await?;
+    println!("Database: OK");
+
+    let pending = db.pending_migrations().await?;
+    if pending.is_empty() {
+        println!("Migrations: up to date");
+    } else {
+        println!("Migrations: {} pending {:?}", pending.len(), pending);
+    }
+
+    attachment::open_store(&config.attachment)?;
+    println!("Attachment store: OK");
+
+    for schedule in &config.summary.schedule {
+        summary::parse_schedule(schedule)?;
+    }
+    println!("Summary schedule: OK");
+    Ok(())
+}
+
+async fn copy(source: &Repositories, target_config: &Path) -> anyhow::Result<()> {
+    let target_config = read_config_file(target_config)?;
+    let target = Database::connect_once(&target_config).await?;
+    if target.backend() == Backend::MySql {
+        target.migrate().await?;
+    }
+    let dump = source.transfer.dump().await?;
+    let result = target.repositories().transfer.load(&dump).await;
+    target.close().await;
+    result?;
+    println!(
+        "Copied {} inspection(s) and {} attachment record(s)",
+        dump.inspections.len(),
+        dump.attachments.len()
+    );
+    Ok(())
+}
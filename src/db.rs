@@ -0,0 +1,206 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{info, warn};
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::{Row, SqlitePool};
+
+use crate::config::{self, Backend, Config};
+use crate::repository::mysql::MySqlRepository;
+use crate::repository::sqlite::SqliteRepository;
+use crate::repository::Repositories;
+use crate::MySqlPool;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// The SQLite counterpart of [`MIGRATOR`], also creating the `tt_*` tables
+pub static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// Pool of the configured [`Backend`]
+#[derive(Clone, Debug)]
+pub enum Database {
+    MySql(MySqlPool),
+    Sqlite(SqlitePool),
+}
+
+impl Database {
+    /// Connects to the configured backend; MySQL is retried as configured in
+    /// `mysql.retry`.
+    pub async fn connect(config: &Config) -> anyhow::Result<Self> {
+        Ok(match config.backend {
+            Backend::MySql => Self::MySql(connect_with_retry(&config.mysql).await?),
+            Backend::Sqlite => Self::Sqlite(connect_sqlite(&config.sqlite).await?),
+        })
+    }
+
+    /// Connects once, without retrying
+    pub async fn connect_once(config: &Config) -> anyhow::Result<Self> {
+        Ok(match config.backend {
+            Backend::MySql => Self::MySql(
+                config
+                    .mysql
+                    .pool_options()
+                    .connect_with(config.mysql.connect_options()?)
+                    .await?,
+            ),
+            Backend::Sqlite => Self::Sqlite(connect_sqlite(&config.sqlite).await?),
+        })
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self {
+            Database::MySql(_) => Backend::MySql,
+            Database::Sqlite(_) => Backend::Sqlite,
+        }
+    }
+
+    fn migrator(&self) -> &'static Migrator {
+        match self {
+            Database::MySql(_) => &MIGRATOR,
+            Database::Sqlite(_) => &SQLITE_MIGRATOR,
+        }
+    }
+
+    /// The repositories over this database
+    pub fn repositories(&self) -> Repositories {
+        match self {
+            Database::MySql(db) => Repositories::new(Arc::new(MySqlRepository::new(db.clone()))),
+            Database::Sqlite(db) => Repositories::new(Arc::new(SqliteRepository::new(db.clone()))),
+        }
+    }
+
+    pub async fn ping(&self) -> sqlx::Result<()> {
+        match self {
+            Database::MySql(db) => {
+                sqlx::query("SELECT 1").execute(db).await?;
+            }
+            Database::Sqlite(db) => {
+                sqlx::query("SELECT 1").execute(db).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn migrate(&self) -> anyhow::Result<()> {
+        info!("Applying migrations...");
+        match self {
+            Database::MySql(db) => MIGRATOR.run(db).await?,
+            Database::Sqlite(db) => SQLITE_MIGRATOR.run(db).await?,
+        }
+        Ok(())
+    }
+
+    /// Returns versions of the migrations not applied yet; all of them if
+    /// none has ever been applied.
+    pub async fn pending_migrations(&self) -> anyhow::Result<Vec<i64>> {
+        let migrations = self.migrator().iter().map(|m| m.version).collect::<Vec<_>>();
+        if migrations.is_empty() {
+            return Ok(vec![]);
+        }
+        let sql = "SELECT version FROM _sqlx_migrations WHERE success = 1";
+        let applied = match self {
+            Database::MySql(db) => {
+                let exists = "SELECT COUNT(*) FROM information_schema.tables \
+                              WHERE table_schema = DATABASE() AND table_name = '_sqlx_migrations'";
+                match sqlx::query_scalar::<_, i64>(exists).fetch_one(db).await? {
+                    0 => vec![],
+                    _ => sqlx::query(sql)
+                        .fetch_all(db)
+                        .await?
+                        .iter()
+                        .map(|r| r.try_get::<i64, _>("version"))
+                        .collect::<sqlx::Result<Vec<_>>>()?,
+                }
+            }
+            Database::Sqlite(db) => {
+                let exists = "SELECT COUNT(*) FROM sqlite_master \
+                              WHERE type = 'table' AND name = '_sqlx_migrations'";
+                match sqlx::query_scalar::<_, i64>(exists).fetch_one(db).await? {
+                    0 => vec![],
+                    _ => sqlx::query(sql)
+                        .fetch_all(db)
+                        .await?
+                        .iter()
+                        .map(|r| r.try_get::<i64, _>("version"))
+                        .collect::<sqlx::Result<Vec<_>>>()?,
+                }
+            }
+        };
+        Ok(migrations
+            .into_iter()
+            .filter(|v| !applied.contains(v))
+            .collect())
+    }
+
+    /// `(size, idle, max)` of the pool
+    pub fn pool_stats(&self) -> (u32, usize, u32) {
+        match self {
+            Database::MySql(db) => (db.size(), db.num_idle(), db.options().get_max_connections()),
+            Database::Sqlite(db) => (db.size(), db.num_idle(), db.options().get_max_connections()),
+        }
+    }
+
+    pub async fn close(&self) {
+        match self {
+            Database::MySql(db) => db.close().await,
+            Database::Sqlite(db) => db.close().await,
+        }
+    }
+}
+
+/// Opens the SQLite file, creating it if missing. The schema is always
+/// migrated, as the server owns all of it.
+async fn connect_sqlite(config: &config::Sqlite) -> anyhow::Result<SqlitePool> {
+    let pool = SqlitePoolOptions::new()
+        .connect_with(config.connect_options())
+        .await?;
+    SQLITE_MIGRATOR.run(&pool).await?;
+    Ok(pool)
+}
+
+/// Connects to the database, retrying with exponential backoff.
+pub async fn connect_with_retry(config: &config::MySql) -> anyhow::Result<MySqlPool> {
+    let retry = &config.retry;
+    let mut backoff = Duration::from_secs(retry.initial_backoff);
+    let max_backoff = Duration::from_secs(retry.max_backoff);
+    let mut attempt = 1_u32;
+    loop {
+        let result: anyhow::Result<MySqlPool> = try {
+            let pool = config
+                .pool_options()
+                .connect_with(config.connect_options()?)
+                .await?;
+            sqlx::query("SELECT 1").execute(&pool).await?;
+            pool
+        };
+        match result {
+            Ok(pool) => return Ok(pool),
+            Err(e) if retry.max_attempts == 0 || attempt < retry.max_attempts => {
+                warn!(
+                    "Failed to connect to the database (attempt {}): {}; retrying in {}",
+                    attempt,
+                    e,
+                    humantime::format_duration(backoff)
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(max_backoff);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Whether the error is caused by the database being unreachable, rather
+/// than by the query itself.
+pub fn is_unavailable(err: &sqlx::Error) -> bool {
+    matches!(
+        err,
+        sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::Io(_)
+            | sqlx::Error::Tls(_)
+            | sqlx::Error::WorkerCrashed
+    )
+}
@@ -0,0 +1,59 @@
+use std::net::SocketAddr;
+
+use axum::extract::{ConnectInfo, Request};
+use axum::http::header::AUTHORIZATION;
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use czttgd_api::ReloadReport;
+
+use crate::handlers::{api_error, handle_errors};
+use crate::{api_ok, mutex_lock, reload, CONFIG};
+
+/// With `admin.token` if set, otherwise only from the loopback interface
+fn authorized(peer: Option<SocketAddr>, headers: &HeaderMap) -> bool {
+    let token = mutex_lock!(CONFIG).admin.token.clone();
+    match token {
+        Some(token) => {
+            let given = headers
+                .get(AUTHORIZATION)
+                .and_then(|x| x.to_str().ok())
+                .and_then(|x| x.strip_prefix("Bearer "));
+            // compared in full, not to leak the matching prefix by timing
+            given.is_some_and(|x| {
+                x.len() == token.len()
+                    && x.bytes().zip(token.bytes()).fold(0, |a, (x, y)| a | (x ^ y)) == 0
+            })
+        }
+        None => peer.is_some_and(|x| x.ip().is_loopback()),
+    }
+}
+
+/// Guards the admin routes, rejecting the requests not [`authorized`] with
+/// 403.
+pub async fn require_admin(
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !authorized(connect_info.map(|x| x.0), request.headers()) {
+        return (StatusCode::FORBIDDEN, api_error!("Forbidden")).into_response();
+    }
+    next.run(request).await
+}
+
+/// Reloads the config, and responds with a [`ReloadReport`].
+#[utoipa::path(
+    post, path = "/admin/reload", tag = "admin",
+    responses(
+        (status = 200, body = crate::ResponseJson<ReloadReport>),
+        (status = 403, description = "Without `admin.token`, or not from the loopback interface if there's none")
+    )
+)]
+pub async fn reload_config() -> impl IntoResponse {
+    let result: anyhow::Result<()> = try {
+        let report = reload::reload().await?;
+        return api_ok!(report);
+    };
+    handle_errors!(result)
+}
@@ -0,0 +1,209 @@
+use axum::body::Bytes;
+use axum::extract::{Multipart, Path};
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::Extension;
+use log::{info, warn};
+use object_store::PutPayload;
+
+use crate::attachment::process_image;
+use crate::handlers::{api_error, handle_errors, Attachment};
+use crate::repository::NewAttachment;
+use crate::{api_ok, mutex_lock, ApiContext, CONFIG};
+
+/// Accepts one or more images as multipart file fields, and responds with
+/// the created [`Attachment`]s.
+#[utoipa::path(
+    post, path = "/inspection/{id}/attachments", tag = "attachment",
+    params(("id" = i64, Path, description = "Inspection id")),
+    request_body(content_type = "multipart/form-data", description = "One or more JPEG, PNG or WebP files"),
+    responses((status = 200, body = crate::ResponseJson<Vec<Attachment>>))
+)]
+pub async fn upload(
+    Extension(api_context): Extension<ApiContext>,
+    Path(inspection_id): Path<i64>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    let store = &api_context.attachment_store;
+    let thumbnail_size = mutex_lock!(CONFIG).attachment.thumbnail_size;
+
+    // of this request, removed if it fails halfway
+    let mut created = vec![];
+    let mut stored = vec![];
+
+    let result: anyhow::Result<()> = try {
+        if !api_context.inspections.exists(inspection_id).await? {
+            return api_error!(format!("No such inspection: {}", inspection_id));
+        }
+
+        while let Some(field) = multipart.next_field().await? {
+            if field.file_name().is_none() {
+                continue;
+            }
+            let filename = field.file_name().map(String::from);
+            let data = field.bytes().await?;
+            let image = {
+                let data = data.clone();
+                tokio::task::spawn_blocking(move || process_image(&data, thumbnail_size)).await??
+            };
+
+            let name = uuid::Uuid::new_v4().simple().to_string();
+            let ext = image.format.extensions_str()[0];
+            let object_key = format!("inspections/{inspection_id}/{name}.{ext}");
+            let thumbnail_key = format!("inspections/{inspection_id}/{name}-thumb.jpg");
+            store
+                .put(&object_key.as_str().into(), PutPayload::from(data.clone()))
+                .await?;
+            stored.push(object_key.clone());
+            store
+                .put(
+                    &thumbnail_key.as_str().into(),
+                    PutPayload::from(image.thumbnail),
+                )
+                .await?;
+            stored.push(thumbnail_key.clone());
+
+            let attachment = NewAttachment {
+                inspection_id,
+                filename,
+                content_type: image.format.to_mime_type().into(),
+                size: data.len() as i64,
+                width: image.width,
+                height: image.height,
+                object_key: object_key.clone(),
+                thumbnail_key,
+            };
+            let attachment = api_context.attachments.insert(attachment).await?;
+            info!("Attached {} to inspection {}", object_key, inspection_id);
+            created.push(attachment);
+        }
+        if created.is_empty() {
+            return api_error!("No image");
+        }
+        return api_ok!(created);
+    };
+    if result.is_err() {
+        discard(&api_context, &created, &stored).await;
+    }
+    handle_errors!(result)
+}
+
+/// Removes the rows and objects of a failed upload, so none are orphaned.
+async fn discard(api_context: &ApiContext, created: &[Attachment], keys: &[String]) {
+    for attachment in created {
+        if let Err(e) = api_context.attachments.delete(attachment.id).await {
+            warn!("Failed to delete attachment {}: {}", attachment.id, e);
+        }
+    }
+    for key in keys {
+        if let Err(e) = api_context
+            .attachment_store
+            .delete(&key.as_str().into())
+            .await
+        {
+            warn!("Failed to delete {}: {}", key, e);
+        }
+    }
+}
+
+#[utoipa::path(
+    get, path = "/inspection/{id}/attachments", tag = "attachment",
+    params(("id" = i64, Path, description = "Inspection id")),
+    responses((status = 200, body = crate::ResponseJson<Vec<Attachment>>))
+)]
+pub async fn list(
+    Extension(api_context): Extension<ApiContext>,
+    Path(inspection_id): Path<i64>,
+) -> impl IntoResponse {
+    let result: anyhow::Result<()> = try {
+        let attachments = api_context.attachments.list(inspection_id).await?;
+        return api_ok!(attachments);
+    };
+    handle_errors!(result)
+}
+
+async fn fetch_object(
+    api_context: &ApiContext,
+    id: i64,
+    thumbnail: bool,
+) -> anyhow::Result<Option<(String, Bytes)>> {
+    let Some(attachment) = api_context.attachments.get(id).await? else {
+        return Ok(None);
+    };
+    let (key, content_type) = match thumbnail {
+        true => (attachment.thumbnail_key, "image/jpeg".to_string()),
+        false => (attachment.object_key, attachment.content_type),
+    };
+    let data = api_context
+        .attachment_store
+        .get(&key.as_str().into())
+        .await?
+        .bytes()
+        .await?;
+    Ok(Some((content_type, data)))
+}
+
+#[utoipa::path(
+    get, path = "/attachment/{id}", tag = "attachment",
+    params(("id" = i64, Path)),
+    responses((status = 200, description = "The image", body = Vec<u8>, content_type = "image/*"))
+)]
+pub async fn fetch(
+    Extension(api_context): Extension<ApiContext>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let result: anyhow::Result<()> = try {
+        let Some((content_type, data)) = fetch_object(&api_context, id, false).await? else {
+            return api_error!(format!("No such attachment: {}", id));
+        };
+        return ([(CONTENT_TYPE, content_type)], data).into_response();
+    };
+    handle_errors!(result)
+}
+
+#[utoipa::path(
+    get, path = "/attachment/{id}/thumbnail", tag = "attachment",
+    params(("id" = i64, Path)),
+    responses((status = 200, description = "JPEG thumbnail", body = Vec<u8>, content_type = "image/jpeg"))
+)]
+pub async fn fetch_thumbnail(
+    Extension(api_context): Extension<ApiContext>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let result: anyhow::Result<()> = try {
+        let Some((content_type, data)) = fetch_object(&api_context, id, true).await? else {
+            return api_error!(format!("No such attachment: {}", id));
+        };
+        return ([(CONTENT_TYPE, content_type)], data).into_response();
+    };
+    handle_errors!(result)
+}
+
+#[utoipa::path(
+    delete, path = "/attachment/{id}", tag = "attachment",
+    params(("id" = i64, Path)),
+    responses((status = 200, body = crate::ResponseJson<crate::Empty>))
+)]
+pub async fn delete(
+    Extension(api_context): Extension<ApiContext>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let result: anyhow::Result<()> = try {
+        let Some(attachment) = api_context.attachments.get(id).await? else {
+            return api_error!(format!("No such attachment: {}", id));
+        };
+        api_context.attachments.delete(id).await?;
+        for key in [&attachment.object_key, &attachment.thumbnail_key] {
+            // the row is gone already; a leftover object is harmless
+            if let Err(e) = api_context
+                .attachment_store
+                .delete(&key.as_str().into())
+                .await
+            {
+                warn!("Failed to delete {}: {}", key, e);
+            }
+        }
+        return api_ok!(());
+    };
+    handle_errors!(result)
+}
@@ -1,32 +1,30 @@
 use axum::response::{IntoResponse, Response};
 use axum::{debug_handler, extract, Extension};
-use futures::{StreamExt, TryStreamExt};
-use log::info;
 use serde::Deserialize;
-use sqlx::Row;
+use utoipa::IntoParams;
 
 use crate::handlers::handle_errors;
-use crate::{api_ok, include_sql, ApiContext};
+use crate::{api_ok, ApiContext};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Path)]
 pub struct Path {
     stage: i32,
 }
+
+#[utoipa::path(
+    get, path = "/stage/{stage}/devices", tag = "master data", params(Path),
+    responses((status = 200, description = "Machine numbers", body = crate::ResponseJson<Vec<i32>>))
+)]
 #[debug_handler]
 pub async fn devices(
     extract::Path(stage): extract::Path<Path>,
     extension: Extension<ApiContext>,
 ) -> Response {
-    info!("Route: /stage/:stage/devices");
     let stage = stage.stage;
 
     let result: anyhow::Result<()> = try {
-        let db = &extension.db;
-        let devices = sqlx::query(include_sql!("devices")).bind(stage).fetch(db);
-        let devices = devices
-            .map(|x| x.map(|x| x.get::<i32, _>(0)))
-            .try_collect::<Vec<_>>()
-            .await?;
+        let devices = extension.master_data.devices(stage).await?;
         return api_ok!(devices);
     };
     handle_errors!(result)
@@ -0,0 +1,45 @@
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::Extension;
+
+use crate::handlers::error_message;
+use crate::{api_ok, ApiContext, ResponseJson};
+
+/// Liveness; always succeeds as long as the process serves requests.
+#[utoipa::path(
+    get, path = "/health", tag = "meta",
+    responses((status = 200, body = crate::ResponseJson<String>))
+)]
+pub async fn health() -> impl IntoResponse {
+    api_ok!("ok")
+}
+
+/// Readiness; the database is reachable and all migrations are applied.
+#[utoipa::path(
+    get, path = "/ready", tag = "meta",
+    responses(
+        (status = 200, body = crate::ResponseJson<String>),
+        (status = 503, description = "Not ready", body = crate::ResponseJson<crate::Empty>)
+    )
+)]
+pub async fn ready(Extension(api_context): Extension<ApiContext>) -> impl IntoResponse {
+    let db = &api_context.db;
+
+    let result: anyhow::Result<()> = try {
+        db.ping().await?;
+        let pending = db.pending_migrations().await?;
+        if !pending.is_empty() {
+            return not_ready(format!("Pending migrations: {:?}", pending));
+        }
+        return api_ok!("ok");
+    };
+    not_ready(error_message(&result.err().unwrap()))
+}
+
+fn not_ready(message: String) -> axum::response::Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        ResponseJson::<()>::error_msg(message),
+    )
+        .into_response()
+}
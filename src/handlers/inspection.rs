@@ -1,211 +1,253 @@
-use std::time::{SystemTime, UNIX_EPOCH};
-use anyhow::anyhow;
+use std::collections::HashMap;
+use std::time::Duration;
 
-use axum::extract::{Path, Query};
+use axum::extract::{Multipart, Path, Query};
+use axum::http::HeaderMap;
 use axum::response::IntoResponse;
 use axum::{Extension, Form, Json};
-use futures::TryStreamExt;
-use log::{debug, info};
-use regex::Regex;
-use serde::{Deserialize, Serialize};
-use sqlx::mysql::MySqlArguments;
-use sqlx::{Executor, FromRow, MySql, Row};
+use czttgd_api::{
+    BatchOperation, BatchRequest, BatchResult, BatchStatus, ImportQuery, ImportReport, SearchQuery,
+};
+use log::debug;
 
 use crate::handlers::{
-    counter, handle_errors, BreakCause, Breakpoint, InspectionDetails, InspectionForm,
-    InspectionSummary, User,
+    api_error, handle_errors, InspectionDetails, InspectionForm, InspectionSummary,
 };
-use crate::{api_ok, check_from_row, include_sql, mutex_lock, timestamp_secs, ApiContext, MySqlPool, UPDATE_COUNTER};
+use crate::{api_ok, import, metrics, mutex_lock, ApiContext, CONFIG, UPDATE_COUNTER};
 
-/// timestamp+<counter>
-///
-/// `counter` has three-width padding, like 001, 002...
-async fn generate_inspection_id(db: &MySqlPool) -> anyhow::Result<i64> {
-    let id_num = counter::increase(db).await?;
+const IDEMPOTENCY_KEY: &str = "Idempotency-Key";
 
-    let timestamp = timestamp_secs();
-    let id = format!("{timestamp}{:03}", id_num).parse::<i64>()?;
-    Ok(id)
-}
+/// Max length of an `Idempotency-Key`, as stored
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// Max ids of a batch, so it doesn't hold the locks for long
+pub(crate) const MAX_BATCH_SIZE: usize = 1000;
 
+/// Creates an inspection. Retries with the same `Idempotency-Key` return the
+/// original id instead, within `idempotency.window`.
+#[utoipa::path(
+    post, path = "/inspection", tag = "inspection",
+    params(("Idempotency-Key" = Option<String>, Header, description = "Any unique string, like a UUID, of at most 255 bytes")),
+    request_body(content = InspectionForm, content_type = "application/x-www-form-urlencoded"),
+    responses((status = 200, description = "Id of the new inspection", body = crate::ResponseJson<i64>))
+)]
 #[axum::debug_handler]
 pub async fn post_new(
     Extension(api_context): Extension<ApiContext>,
+    headers: HeaderMap,
     Form(form): Form<InspectionForm>,
 ) -> impl IntoResponse {
-    info!("Route: /inspection");
     debug!("Form: {:?}", form);
-    let db = &api_context.db;
+    let device_code = form.device_code;
 
     let result: anyhow::Result<()> = try {
-        let id = generate_inspection_id(db).await?;
+        let inspections = &api_context.inspections;
+        let (id, created) = match headers.get(IDEMPOTENCY_KEY).map(|x| x.to_str()) {
+            None => (inspections.create(form).await?, true),
+            Some(Ok(key)) if !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN => {
+                let window = Duration::from_secs(mutex_lock!(CONFIG).idempotency.window);
+                inspections.create_once(key, window, form).await?
+            }
+            Some(_) => return api_error!(format!("Invalid {}", IDEMPOTENCY_KEY)),
+        };
 
-        let query = sqlx::query(include_sql!("inspection-post"));
-        let query = bind_form(query, form)?;
-        // bind: id
-        let query = query.bind(id);
-        db.execute(query).await?;
-        
-        *mutex_lock!(UPDATE_COUNTER) += 1;
+        if created {
+            *mutex_lock!(UPDATE_COUNTER) += 1;
+            metrics::record_inspection(&*api_context.master_data, "created", device_code).await;
+        } else {
+            debug!("Replayed {}: {}", IDEMPOTENCY_KEY, id);
+        }
 
         return api_ok!(id);
     };
     handle_errors!(result)
 }
 
-fn bind_form(
-    query: sqlx::query::Query<MySql, MySqlArguments>,
-    form: InspectionForm,
-) -> anyhow::Result<sqlx::query::Query<MySql, MySqlArguments>> {
-    // They use `char(1)`. According the conversion,
-    // we must insert a `&str`.
-    let break_flag = match form.break_flag {
-        true => "1",
-        false => "0",
-    };
-
-    return Ok(query
-        .bind(form.creator)
-        .bind(form.device_code)
-        .bind(form.creation_time)
-        .bind(form.product_spec)
-        .bind(form.wire_number)
-        .bind(form.wire_type)
-        .bind(form.break_spec)
-        .bind(form.wire_batch_code)
-        .bind(form.stick_batch_code)
-        .bind(form.warehouse)
-        .bind(form.product_time)
-        .bind(break_flag)
-        .bind(form.breakpoint_a)
-        .bind(form.breakpoint_b)
-        .bind(form.comments)
-        .bind(form.device_category)
-        .bind(form.break_cause_a));
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-pub struct SearchQuery {
-    filter: String,
-    stage: u32,
-    limit: Option<u64>,
-    offset: Option<u64>,
-}
-
 /// Performs a search, and returns a set of [`InspectionSummary`].
+#[utoipa::path(
+    get, path = "/inspection/search", tag = "inspection", params(SearchQuery),
+    responses((status = 200, body = crate::ResponseJson<Vec<InspectionSummary>>))
+)]
 #[axum::debug_handler]
 pub async fn search(
     Extension(api_context): Extension<ApiContext>,
     Query(api_query): Query<SearchQuery>,
 ) -> impl IntoResponse {
-    info!("Route: /inspection/search");
     debug!("Query: {:?}", api_query);
-    let db = &api_context.db;
-    let offset = api_query.offset.unwrap_or_default();
-    let limit = api_query.limit.unwrap_or(100);
 
     let r: anyhow::Result<()> = try {
-        let mut query = sqlx::query(include_sql!("inspection-search"));
-        query = query.bind(api_query.stage as i32);
-        for _ in 0..11 {
-            query = query.bind(&api_query.filter);
-        }
-        // limit, offset
-        let query = query.bind(limit).bind(offset);
-        let mut stream = query.fetch(db);
-        let mut collected = vec![];
-        while let Some(row) = stream.try_next().await? {
-            let summary = InspectionSummary::from_row(&row)?;
-            let summary = InspectionSummary {
-                break_cause_a: check_from_row(&row, "br_a", |r| {
-                    BreakCause::from_row_prefixed(r, "br_a")
-                })?,
-                break_cause_b: check_from_row(&row, "br_a", |r| {
-                    BreakCause::from_row_prefixed(r, "br_b")
-                })?,
-                ..summary
-            };
-            collected.push(summary);
-        }
+        let collected = api_context.inspections.search(&api_query).await?;
         return api_ok!(collected);
     };
     handle_errors!(r)
 }
 
+#[utoipa::path(
+    get, path = "/inspection/{id}/details", tag = "inspection",
+    params(("id" = i64, Path)),
+    responses((status = 200, body = crate::ResponseJson<InspectionDetails>))
+)]
 #[axum::debug_handler]
 pub async fn query_details(
     Extension(api_context): Extension<ApiContext>,
     path: Path<(i64,)>,
 ) -> impl IntoResponse {
-    info!("Route: /inspection/:id/details");
     debug!("Path: {:?}", path);
     let id = path.0 .0;
-    let db = &api_context.db;
 
     let result: anyhow::Result<()> = try {
-        let r = sqlx::query(include_sql!("inspection-details"))
-            .bind(id)
-            .fetch_one(db)
-            .await?;
-
-        let details = InspectionDetails::from_row(&r)?;
-        let details = InspectionDetails {
-            breakpoint_a: check_from_row(&r, "breakpoint_a", |r| {
-                Breakpoint::from_row_prefixed(r, "bp_a")
-            })?,
-            break_cause_a: check_from_row(&r, "break_cause_a", |r| {
-                BreakCause::from_row_prefixed(r, "br_a")
-            })?,
-            break_cause_b: check_from_row(&r, "break_cause_b", |r| {
-                BreakCause::from_row_prefixed(r, "br_b")
-            })?,
-            inspector: check_from_row(&r, "inspector_user_name", |r| {
-                User::from_row_prefixed(r, "inspector")
-            })?,
-            ..details
+        let Some(details) = api_context.inspections.details(id).await? else {
+            return api_error!(format!("No such inspection: {}", id));
         };
         return api_ok!(details);
     };
     handle_errors!(result)
 }
 
+#[utoipa::path(
+    put, path = "/inspection/{id}", tag = "inspection",
+    params(("id" = i64, Path)),
+    request_body(content = InspectionForm, content_type = "application/x-www-form-urlencoded"),
+    responses((status = 200, body = crate::ResponseJson<crate::Empty>))
+)]
 #[axum::debug_handler]
 pub async fn update(
     Extension(api_context): Extension<ApiContext>,
     Path(path): Path<(i64,)>,
     Form(form): Form<InspectionForm>,
 ) -> impl IntoResponse {
-    info!("Route: /inspection/:id");
     let id = path.0;
-    let db = &api_context.db;
+    let device_code = form.device_code;
 
     let result: anyhow::Result<()> = try {
-        let query = sqlx::query(include_sql!("inspection-update"));
-        let query = bind_form(query, form)?;
-        // WHERE id = ?
-        let query = query.bind(id);
-        query.execute(db).await?;
-        *mutex_lock!(UPDATE_COUNTER) += 1;
+        if api_context.inspections.update(id, form).await? {
+            *mutex_lock!(UPDATE_COUNTER) += 1;
+            metrics::record_inspection(&*api_context.master_data, "updated", device_code).await;
+        }
         return api_ok!(());
     };
     handle_errors!(result)
 }
 
+#[utoipa::path(
+    get, path = "/inspection/count", tag = "inspection",
+    responses((status = 200, body = crate::ResponseJson<i64>))
+)]
 #[axum::debug_handler]
 pub async fn count(Extension(api_context): Extension<ApiContext>) -> impl IntoResponse {
-    info!("Route: /inspection/count");
-    let db = &api_context.db;
     let r: anyhow::Result<()> = try {
-        let r = sqlx::query(include_sql!("inspection-count"))
-            .fetch_one(db)
-            .await?;
-        let count: i64 = r.try_get("c")?;
-
+        let count = api_context.inspections.count().await?;
         return api_ok!(count);
     };
     handle_errors!(r)
 }
 
+/// Applies one operation to many inspections, all in one transaction.
+/// Ids the operation doesn't apply to are skipped, and reported so.
+#[utoipa::path(
+    post, path = "/inspection/batch", tag = "inspection",
+    request_body = BatchRequest,
+    responses((status = 200, description = "One result per id, in order", body = crate::ResponseJson<Vec<BatchResult>>))
+)]
+#[axum::debug_handler]
+pub async fn batch(
+    Extension(api_context): Extension<ApiContext>,
+    Json(request): Json<BatchRequest>,
+) -> impl IntoResponse {
+    debug!("Request: {:?}", request);
+
+    let result: anyhow::Result<()> = try {
+        if request.ids.is_empty() || request.ids.len() > MAX_BATCH_SIZE {
+            return api_error!(format!("Expected 1 to {} ids", MAX_BATCH_SIZE));
+        }
+        match &request.operation {
+            BatchOperation::Finalize { inspector, .. } if inspector.trim().is_empty() => {
+                return api_error!("No inspector");
+            }
+            BatchOperation::Finalize { cause, .. } | BatchOperation::ReassignCause { cause } => {
+                let causes = api_context.master_data.break_causes().await?;
+                if !causes.iter().any(|x| x.id == *cause) {
+                    return api_error!(format!("No such break cause: {}", cause));
+                }
+            }
+            BatchOperation::Delete | BatchOperation::Restore => {}
+        }
+
+        let results = api_context
+            .inspections
+            .batch(&request.ids, &request.operation)
+            .await?;
+        // inspections changed per machine
+        let mut machines = HashMap::<i32, u64>::new();
+        for (result, device_code) in &results {
+            if let (BatchStatus::Done, Some(device_code)) = (&result.status, device_code) {
+                *machines.entry(*device_code).or_default() += 1;
+            }
+        }
+        if !machines.is_empty() {
+            *mutex_lock!(UPDATE_COUNTER) += 1;
+        }
+        let operation = match request.operation {
+            BatchOperation::Delete => "deleted",
+            _ => "updated",
+        };
+        for (device_code, count) in machines {
+            metrics::record_inspections(&*api_context.master_data, operation, device_code, count)
+                .await;
+        }
+        let results = results.into_iter().map(|(x, _)| x).collect::<Vec<_>>();
+        return api_ok!(results);
+    };
+    handle_errors!(result)
+}
+
+/// Imports inspections from a CSV file or an Excel workbook, given as the
+/// first multipart file field. See [`crate::import`] for the columns.
+#[utoipa::path(
+    post, path = "/inspection/import", tag = "inspection", params(ImportQuery),
+    request_body(content_type = "multipart/form-data", description = "A .csv, .xlsx or .xls file"),
+    responses((status = 200, description = "Nothing is imported if there are errors", body = crate::ResponseJson<ImportReport>))
+)]
+pub async fn import(
+    Extension(api_context): Extension<ApiContext>,
+    Query(api_query): Query<ImportQuery>,
+    mut multipart: Multipart,
+) -> impl IntoResponse {
+    debug!("Query: {:?}", api_query);
+
+    let result: anyhow::Result<()> = try {
+        let map = import::parse_map(api_query.map.as_deref().unwrap_or_default())?;
+        let mut content = None;
+        while let Some(field) = multipart.next_field().await? {
+            if field.file_name().is_some() {
+                content = Some(field.bytes().await?);
+                break;
+            }
+        }
+        let Some(content) = content else {
+            return api_error!("No file");
+        };
+
+        let report = import::import(
+            content.to_vec(),
+            &map,
+            api_query.dry_run,
+            &*api_context.inspections,
+            &*api_context.master_data,
+        )
+        .await?;
+        if !report.dry_run && report.imported > 0 {
+            *mutex_lock!(UPDATE_COUNTER) += 1;
+        }
+        return api_ok!(report);
+    };
+    handle_errors!(result)
+}
+
+#[utoipa::path(
+    get, path = "/inspection/updatecounter", tag = "inspection",
+    responses((status = 200, description = "Bare number, without the envelope", body = u32))
+)]
 #[axum::debug_handler]
 pub async fn update_counter(Extension(api_context): Extension<ApiContext>) -> impl IntoResponse {
     Json(*UPDATE_COUNTER.lock().unwrap())
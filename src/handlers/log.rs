@@ -1,39 +1,327 @@
-use std::fs::{create_dir, File};
 use std::io;
-use std::io::{BufWriter, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::time::{Duration, SystemTime};
 
 use anyhow::anyhow;
-use axum::body::Bytes;
-use axum::extract::Multipart;
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+use axum::body::Body;
+use axum::extract::multipart::Field;
+use axum::extract::{Multipart, Query};
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
 use axum::response::IntoResponse;
-use log::info;
+use futures::TryStreamExt;
+use log::{info, warn};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio_util::io::{ReaderStream, StreamReader};
 
-use crate::{api_ok, timestamp_secs};
+use czttgd_api::{ListQuery, LogMetadata};
+
+use crate::config::LogUpload;
 use crate::handlers::{api_error, handle_errors};
+use crate::{api_ok, metrics, mutex_lock, CONFIG};
+
+/// How often [`run_log_retention`] applies the retention
+const RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+fn upload_config() -> LogUpload {
+    mutex_lock!(CONFIG).log_upload.clone()
+}
+
+fn generate_id() -> String {
+    let millis = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let random = uuid::Uuid::new_v4().simple().to_string();
+    format!("{}-{}", millis, &random[..8])
+}
+
+/// Resolves `<dir>/<id>.<ext>`, rejecting ids that could escape the directory.
+fn log_path(dir: &Path, id: &str, ext: &str) -> anyhow::Result<PathBuf> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(anyhow!("Invalid log id: {}", id));
+    }
+    Ok(dir.join(format!("{id}.{ext}")))
+}
+
+/// Compression of an uploaded log, detected from its part's
+/// `Content-Type` or file extension
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
 
+impl Compression {
+    fn detect(field: &Field) -> Self {
+        let content_type = field.content_type().unwrap_or_default();
+        let filename = field.file_name().unwrap_or_default();
+        if matches!(content_type, "application/gzip" | "application/x-gzip")
+            || filename.ends_with(".gz")
+        {
+            Self::Gzip
+        } else if content_type == "application/zstd" || filename.ends_with(".zst") {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+
+    /// The original filename before compression
+    fn strip_extension(self, filename: &str) -> &str {
+        let ext = match self {
+            Compression::None => return filename,
+            Compression::Gzip => ".gz",
+            Compression::Zstd => ".zst",
+        };
+        filename.strip_suffix(ext).unwrap_or(filename)
+    }
+}
+
+/// Accepts a multipart form with the log content and optional `device`,
+/// `user` and `appVersion` text fields. The content can be compressed with
+/// gzip or zstd, and is stored decompressed.
+///
+/// For compatibility with older clients, any field other than the
+/// metadata ones is taken as the log content.
+#[utoipa::path(
+    post, path = "/log", tag = "log",
+    request_body(content_type = "multipart/form-data", description = "The log file, optionally gzip or zstd compressed, and `device`, `user` and `appVersion` text fields"),
+    responses((status = 200, description = "Id of the upload", body = crate::ResponseJson<String>))
+)]
 pub async fn upload_log(mut multipart: Multipart) -> impl IntoResponse {
-    info!("Route: /log");
+    let config = upload_config();
+    let log_dir = Path::new(&config.dir);
+    let mut metadata = LogMetadata {
+        id: generate_id(),
+        device: None,
+        user: None,
+        app_version: None,
+        original_filename: None,
+        size: 0,
+        received_time: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+    };
+    let mut content_path = None;
 
     let result: anyhow::Result<()> = try {
-        let on_error = || anyhow!("Unwrap None on multipart");
-        let first = multipart.next_field().await?.ok_or_else(on_error)?;
-        let name = first.name().ok_or_else(on_error)?;
-        let bytes = first.bytes().await?;
-        save_log(bytes)?;
-        return api_ok!(());
+        while let Some(field) = multipart.next_field().await? {
+            match field.name() {
+                Some("device") => metadata.device = Some(field.text().await?),
+                Some("user") => metadata.user = Some(field.text().await?),
+                Some("appVersion") => metadata.app_version = Some(field.text().await?),
+                _ if content_path.is_none() => {
+                    let compression = Compression::detect(&field);
+                    metadata.original_filename = field
+                        .file_name()
+                        .map(|x| compression.strip_extension(x).to_string());
+                    tokio::fs::create_dir_all(log_dir).await?;
+                    let path = log_path(log_dir, &metadata.id, "log")?;
+                    info!("Receive and write log: {}", path.display());
+                    content_path = Some(path.clone());
+                    metadata.size =
+                        write_content(field, compression, &path, config.max_decompressed_size)
+                            .await?;
+                }
+                _ => {}
+            }
+        }
+        if content_path.is_none() {
+            return api_error!("No log content");
+        }
+        metrics::LOG_UPLOAD_BYTES.observe(metadata.size as f64);
+
+        // written last, so only complete uploads are listed
+        let metadata_path = log_path(log_dir, &metadata.id, "json")?;
+        tokio::fs::write(metadata_path, serde_json::to_vec_pretty(&metadata)?).await?;
+        return api_ok!(metadata.id);
     };
+    if let Some(p) = content_path {
+        // partially written
+        let _ = tokio::fs::remove_file(p).await;
+    }
     handle_errors!(result)
 }
 
-fn save_log(log: Bytes) -> io::Result<()> {
-    let log_dir = Path::new("./uploaded-log");
-    if !log_dir.exists() {
-        create_dir(log_dir)?;
+/// Streams the field to `path`, decompressing on the fly, and returns
+/// the number of bytes written.
+async fn write_content(
+    field: Field<'_>,
+    compression: Compression,
+    path: &Path,
+    max_size: u64,
+) -> anyhow::Result<u64> {
+    let reader = StreamReader::new(field.map_err(io::Error::other));
+    let reader: Pin<Box<dyn AsyncRead + Send + '_>> = match compression {
+        Compression::None => Box::pin(reader),
+        Compression::Gzip => Box::pin(GzipDecoder::new(reader)),
+        Compression::Zstd => Box::pin(ZstdDecoder::new(reader)),
+    };
+    // one more byte to tell if it's over the limit
+    let mut reader = reader.take(max_size + 1);
+    let mut file = tokio::fs::File::create(path).await?;
+    let size = tokio::io::copy(&mut reader, &mut file).await?;
+    if size > max_size {
+        return Err(anyhow!("Log exceeds the size limit of {} bytes", max_size));
+    }
+    file.flush().await?;
+    Ok(size)
+}
+
+/// All uploads, the newest first
+async fn list_metadata(dir: &Path) -> anyhow::Result<Vec<LogMetadata>> {
+    if !tokio::fs::try_exists(dir).await? {
+        return Ok(vec![]);
+    }
+    let mut collected = vec![];
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|x| x.to_str()) != Some("json") {
+            continue;
+        }
+        match serde_json::from_slice::<LogMetadata>(&tokio::fs::read(&path).await?) {
+            Ok(m) => collected.push(m),
+            Err(e) => warn!("Invalid log metadata {}: {}", path.display(), e),
+        }
+    }
+    collected.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(collected)
+}
+
+async fn remove_log(dir: &Path, id: &str) -> anyhow::Result<()> {
+    // metadata first, so a half-removed upload is never listed
+    tokio::fs::remove_file(log_path(dir, id, "json")?).await?;
+    match tokio::fs::remove_file(log_path(dir, id, "log")?).await {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Removes uploads older than `max_age_days`, then the oldest ones until
+/// the total size is within `max_total_size`. Uploads failing to be removed
+/// are skipped.
+async fn apply_retention(config: &LogUpload) -> anyhow::Result<()> {
+    let dir = Path::new(&config.dir);
+    let mut logs = list_metadata(dir).await?;
+
+    if let Some(days) = config.max_age_days {
+        let max_age = Duration::from_secs(days * 24 * 60 * 60);
+        let now = SystemTime::now();
+        let expired = |m: &LogMetadata| match humantime::parse_rfc3339(&m.received_time) {
+            Ok(t) => now.duration_since(t).unwrap_or_default() > max_age,
+            Err(_) => false,
+        };
+        for m in logs.iter().filter(|m| expired(m)) {
+            info!("Remove expired log: {}", m.id);
+            if let Err(e) = remove_log(dir, &m.id).await {
+                warn!("Failed to remove log {}: {}", m.id, e);
+            }
+        }
+        logs.retain(|m| !expired(m));
+    }
+
+    if let Some(max_total_size) = config.max_total_size {
+        let mut total: u64 = logs.iter().map(|m| m.size).sum();
+        // oldest at the end
+        while total > max_total_size {
+            let Some(m) = logs.pop() else {
+                break;
+            };
+            info!("Remove log over the size cap: {}", m.id);
+            match remove_log(dir, &m.id).await {
+                Ok(_) => total -= m.size,
+                Err(e) => warn!("Failed to remove log {}: {}", m.id, e),
+            }
+        }
     }
-    let file_path = log_dir.join(format!("{}", timestamp_secs()));
-    info!("Receive and write log: {}", file_path.display());
-    let mut writer = BufWriter::new(File::create(file_path)?);
-    writer.write_all(&log)?;
     Ok(())
 }
+
+/// Applies `log_upload.max_age_days` and `max_total_size` every
+/// [`RETENTION_INTERVAL`], forever.
+pub async fn run_log_retention() {
+    let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = apply_retention(&upload_config()).await {
+            warn!("Failed to apply the log retention: {}", e);
+        }
+    }
+}
+
+#[utoipa::path(
+    get, path = "/logs", tag = "log", params(ListQuery),
+    responses(
+        (status = 200, body = crate::ResponseJson<Vec<LogMetadata>>),
+        (status = 403, description = "Not authorized like `/admin/reload`")
+    )
+)]
+pub async fn list_logs(Query(query): Query<ListQuery>) -> impl IntoResponse {
+    let result: anyhow::Result<()> = try {
+        let config = upload_config();
+        let mut logs = list_metadata(Path::new(&config.dir)).await?;
+        logs.retain(|m| {
+            (query.device.is_none() || m.device == query.device)
+                && (query.user.is_none() || m.user == query.user)
+        });
+        return api_ok!(logs);
+    };
+    handle_errors!(result)
+}
+
+#[utoipa::path(
+    get, path = "/log/{id}", tag = "log",
+    params(("id" = String, Path)),
+    responses(
+        (status = 200, description = "The log file", body = Vec<u8>, content_type = "application/octet-stream"),
+        (status = 403, description = "Not authorized like `/admin/reload`")
+    )
+)]
+pub async fn download_log(
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    let result: anyhow::Result<()> = try {
+        let config = upload_config();
+        let dir = Path::new(&config.dir);
+        let metadata: LogMetadata =
+            serde_json::from_slice(&tokio::fs::read(log_path(dir, &id, "json")?).await?)?;
+        let file = tokio::fs::File::open(log_path(dir, &id, "log")?).await?;
+        let filename = metadata
+            .original_filename
+            .unwrap_or_else(|| format!("{}.log", metadata.id))
+            .replace(['"', '\\', '\r', '\n'], "_");
+        return (
+            [
+                (CONTENT_TYPE, "application/octet-stream".to_string()),
+                (
+                    CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", filename),
+                ),
+            ],
+            Body::from_stream(ReaderStream::new(file)),
+        )
+            .into_response();
+    };
+    handle_errors!(result)
+}
+
+#[utoipa::path(
+    delete, path = "/log/{id}", tag = "log",
+    params(("id" = String, Path)),
+    responses(
+        (status = 200, body = crate::ResponseJson<crate::Empty>),
+        (status = 403, description = "Not authorized like `/admin/reload`")
+    )
+)]
+pub async fn delete_log(axum::extract::Path(id): axum::extract::Path<String>) -> impl IntoResponse {
+    let result: anyhow::Result<()> = try {
+        let config = upload_config();
+        remove_log(Path::new(&config.dir), &id).await?;
+        info!("Removed log: {}", id);
+        return api_ok!(());
+    };
+    handle_errors!(result)
+}
@@ -0,0 +1,33 @@
+use axum::extract::{Path, Query};
+use axum::response::IntoResponse;
+use axum::Extension;
+use log::debug;
+
+use czttgd_api::{HistoryQuery, MachineHistory};
+
+use crate::handlers::{api_error, handle_errors};
+use crate::{api_ok, ApiContext};
+
+/// Timeline of breaks, most frequent causes and breakpoints, and the MTBF
+/// of a single machine.
+#[utoipa::path(
+    get, path = "/machine/{code}/history", tag = "machine",
+    params(("code" = i32, Path, description = "Machine number"), HistoryQuery),
+    responses((status = 200, body = crate::ResponseJson<MachineHistory>))
+)]
+#[axum::debug_handler]
+pub async fn history(
+    Extension(api_context): Extension<ApiContext>,
+    Path(code): Path<i32>,
+    Query(api_query): Query<HistoryQuery>,
+) -> impl IntoResponse {
+    debug!("Query: {:?}", api_query);
+
+    let result: anyhow::Result<()> = try {
+        let Some(history) = api_context.inspections.history(code, &api_query).await? else {
+            return api_error!(format!("No such machine: {}", code));
+        };
+        return api_ok!(history);
+    };
+    handle_errors!(result)
+}
@@ -0,0 +1,18 @@
+use axum::http::header::CONTENT_TYPE;
+use axum::response::IntoResponse;
+use axum::Extension;
+
+use crate::handlers::handle_errors;
+use crate::{metrics, ApiContext};
+
+#[utoipa::path(
+    get, path = "/metrics", tag = "meta",
+    responses((status = 200, description = "Prometheus text format", body = String, content_type = "text/plain"))
+)]
+pub async fn metrics(Extension(api_context): Extension<ApiContext>) -> impl IntoResponse {
+    let result: anyhow::Result<()> = try {
+        let text = metrics::gather(&api_context.db)?;
+        return ([(CONTENT_TYPE, prometheus::TEXT_FORMAT)], text).into_response();
+    };
+    handle_errors!(result)
+}
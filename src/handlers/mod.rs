@@ -1,215 +1,177 @@
+use std::collections::BTreeMap;
 use std::fmt;
 use std::sync::Mutex;
 
+use axum::extract::DefaultBodyLimit;
 use axum::response::IntoResponse;
+use axum::routing::MethodRouter;
 use axum::Router;
-use log::info;
 use once_cell::sync::Lazy;
-use serde::{Deserialize, Serialize};
-use sqlx::mysql::MySqlRow;
-use sqlx::types::BigDecimal;
-use sqlx::Row;
+use utoipa_swagger_ui::{Config, SwaggerUi};
 
-use crate::{mutex_lock, RefId};
+use crate::{db, mutex_lock, CONFIG};
 
+mod admin;
+mod attachment;
 mod breakpoint;
 pub mod demo;
 mod device;
+mod health;
+mod machine;
+#[path = "metrics.rs"]
+mod metrics_router;
 pub mod inspection;
+mod sync;
 mod users;
-mod counter;
 
 #[path = "log.rs"]
 mod log_router;
 mod ping;
+mod openapi;
+mod report;
 
-static COLLECTED_ROUTES: Lazy<Mutex<Vec<&'static str>>> =
-    Lazy::new(|| Mutex::new(Default::default()));
+pub use czttgd_api::{
+    Attachment, BreakCause, Breakpoint, InspectionDetails, InspectionForm, InspectionSummary, User,
+};
+pub use log_router::run_log_retention;
 
-macro add_route($router:expr, $t:tt $path:literal, $f:expr) {
-    paste::paste! {
-        $router = $router.route($path, ::axum::routing::[<$t:lower>]($f));
-        mutex_lock!(COLLECTED_ROUTES).push(concat!(stringify!([<$t:upper>]), " ", $path));
-    }
-}
+/// `(method, path)` of routes, without the version prefix
+type RouteList = Vec<(&'static str, &'static str)>;
 
-pub fn router() -> Router {
-    let mut router = Router::new();
-    add_route!(router, GET "/routes", list_routes);
-    add_route!(router, GET "/ping", ping::ping);
-    add_route!(router, GET "/stage/:stage/devices", device::devices);
-    add_route!(router, GET "/users", users::all_users);
-    add_route!(router, GET "/break/causes", breakpoint::all_break_reasons);
-    add_route!(router, GET "/break/points", breakpoint::all_breakpoints);
-    add_route!(router, POST "/inspection", inspection::post_new);
-    add_route!(router, GET "/inspection/search", inspection::search);
-    add_route!(router, GET "/inspection/:id/details", inspection::query_details);
-    add_route!(router, PUT "/inspection/:id", inspection::update);
-    add_route!(router, GET "/inspection/count", inspection::count);
-    add_route!(router, POST "/log", log_router::upload_log);
-    add_route!(router, GET "/inspection/updatecounter", inspection::update_counter);
-    router
-}
+/// Routes of each API version
+static COLLECTED_ROUTES: Lazy<Mutex<Vec<(&'static str, RouteList)>>> =
+    Lazy::new(|| Mutex::new(Default::default()));
 
-pub async fn list_routes() -> impl IntoResponse {
-    info!("Route: /routes");
-    let mut content = String::new();
-    use fmt::Write;
-    for &line in &*mutex_lock!(COLLECTED_ROUTES) {
-        writeln!(&mut content, "{}", line).unwrap();
-    }
-    content
-}
+/// Name and routes of an API version
+type Version = (&'static str, fn() -> Routes);
 
-#[derive(Deserialize, Debug, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct InspectionForm {
-    pub creator: RefId,
-    pub device_code: i32,
-    pub device_category: String,
-    pub creation_time: String,
-    pub product_spec: Option<String>,
-    pub wire_number: Option<i32>,
-    pub wire_type: Option<String>,
-    pub break_spec: String,
-    pub wire_batch_code: Option<String>,
-    pub stick_batch_code: Option<String>,
-    pub warehouse: Option<String>,
-    /// 是否拉丝池内断线
-    pub break_flag: bool,
-    /// 拉丝池 BigDecimal
-    pub breakpoint_b: Option<String>,
-    /// 非拉丝池 ref
-    pub breakpoint_a: Option<RefId>,
-    /// 初检原因
-    pub break_cause_a: Option<RefId>,
-    pub comments: Option<String>,
-    pub product_time: Option<String>,
-}
+/// API versions, each mounted at `/<name>`. The first one is also mounted at
+/// the root for clients from before the versioning.
+///
+/// To change a handler incompatibly, add a `v2` that starts from `v1()` and
+/// replaces just that route with [`add_route!`], then list it here.
+const VERSIONS: &[Version] = &[("v1", v1)];
 
-#[derive(Serialize, Debug, sqlx::FromRow)]
-#[serde(rename_all = "camelCase")]
-pub struct InspectionDetails {
-    id: i64,
-    device_code: i32,
-    device_category: String,
-    /// ref
-    #[sqlx(flatten)]
-    creator: User,
-    creation_time: String,
-    inspection_flag: i32,
-    product_spec: Option<String>,
-    wire_num: Option<i32>,
-    wire_type: Option<String>,
-    break_spec: String,
-    wire_batch_code: Option<String>,
-    stick_batch_code: Option<String>,
-    warehouse: Option<String>,
-    product_time: Option<String>,
-    break_flag: bool,
-    breakpoint_b: Option<BigDecimal>,
-    /// ref
-    #[sqlx(skip)]
-    breakpoint_a: Option<Breakpoint>,
-    /// ref
-    #[sqlx(skip)]
-    break_cause_a: Option<BreakCause>,
-    /// ref
-    #[sqlx(skip)]
-    break_cause_b: Option<BreakCause>,
-    comments: Option<String>,
-    /// ref
-    #[sqlx(skip)]
-    inspector: Option<User>,
-    inspection_time: Option<String>,
-}
+/// Routes of an API version, by path and method
+#[derive(Default, Clone)]
+pub struct Routes(BTreeMap<&'static str, BTreeMap<&'static str, MethodRouter>>);
 
-#[derive(Serialize, Debug, sqlx::FromRow)]
-#[serde(rename_all = "camelCase")]
-pub struct InspectionSummary {
-    id: i64,
-    device_code: i32,
-    #[sqlx(skip)]
-    break_cause_a: Option<BreakCause>,
-    #[sqlx(skip)]
-    break_cause_b: Option<BreakCause>,
-    break_flag: bool,
-    break_spec: String,
-    product_spec: Option<String>,
-    #[sqlx(flatten)]
-    creator: User,
-    creation_time: String,
-    /// 0: 已初检 1: 已终检
-    inspection_flag: i32,
-}
+impl Routes {
+    /// Adds a route, replacing the one with the same method and path if any.
+    pub fn insert(&mut self, method: &'static str, path: &'static str, route: MethodRouter) {
+        self.0.entry(path).or_default().insert(method, route);
+    }
 
-#[derive(sqlx::FromRow, Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct User {
-    #[sqlx(rename = "user_id")]
-    id: i32,
-    #[sqlx(rename = "user_name")]
-    name: String,
-    #[sqlx(rename = "user_enable_state")]
-    enable_state: i32,
-    #[sqlx(rename = "user_user_type")]
-    user_type: String,
-}
+    fn listing(&self) -> RouteList {
+        self.0
+            .iter()
+            .flat_map(|(&path, methods)| methods.keys().map(move |&method| (method, path)))
+            .collect()
+    }
 
-#[derive(sqlx::FromRow, Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Breakpoint {
-    #[sqlx(rename = "bp_id")]
-    id: i32,
-    #[sqlx(rename = "bp_name")]
-    breakpoint: Option<String>,
-    #[sqlx(rename = "bp_enable_state")]
-    enable_state: i32,
+    fn into_router(self) -> Router {
+        let mut router = Router::new();
+        for (path, methods) in self.0 {
+            let route = methods.into_values().reduce(MethodRouter::merge).unwrap();
+            router = router.route(path, route);
+        }
+        router
+    }
 }
 
-#[derive(sqlx::FromRow, Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct BreakCause {
-    #[sqlx(rename = "cause_id")]
-    id: i32,
-    #[sqlx(rename = "cause_type")]
-    r#type: Option<String>,
-    #[sqlx(rename = "cause_name")]
-    cause: Option<String>,
-    #[sqlx(rename = "cause_enable_state")]
-    enable_state: i32,
+macro add_route {
+($routes:expr, $t:tt $path:literal, $f:expr) => {
+        paste::paste! {
+            $routes.insert(stringify!([<$t:upper>]), $path, ::axum::routing::[<$t:lower>]($f));
+        }
+    },
+// with a layer only applied to this route
+($routes:expr, $t:tt $path:literal, $f:expr, $layer:expr) => {
+        paste::paste! {
+            $routes.insert(stringify!([<$t:upper>]), $path, ::axum::routing::[<$t:lower>]($f).layer($layer));
+        }
+    }
 }
 
-impl BreakCause {
-    pub fn from_row_prefixed(row: &MySqlRow, prefix: &str) -> sqlx::Result<Self> {
-        Ok(Self {
-            id: row.try_get(format!("{prefix}_cause_id").as_str())?,
-            r#type: row.try_get(format!("{prefix}_cause_type").as_str())?,
-            cause: row.try_get(format!("{prefix}_cause_name").as_str())?,
-            enable_state: row.try_get(format!("{prefix}_cause_enable_state").as_str())?,
-        })
+pub fn router() -> Router {
+    let mut router = Router::new();
+    let mut collected = Vec::new();
+    for (i, &(name, routes)) in VERSIONS.iter().enumerate() {
+        let routes = routes();
+        collected.push((name, routes.listing()));
+        let versioned = routes.into_router();
+        if i == 0 {
+            router = router.merge(versioned.clone());
+        }
+        router = router.nest(&format!("/{name}"), versioned);
     }
+    *mutex_lock!(COLLECTED_ROUTES) = collected;
+
+    // the document is served by the `/openapi.json` routes
+    router.merge(SwaggerUi::new("/swagger-ui").config(Config::from("/openapi.json")))
 }
 
-impl User {
-    pub fn from_row_prefixed(row: &MySqlRow, prefix: &str) -> sqlx::Result<Self> {
-        Ok(Self {
-            id: row.try_get(format!("{prefix}_user_id").as_str())?,
-            name: row.try_get(format!("{prefix}_user_name").as_str())?,
-            enable_state: row.try_get(format!("{prefix}_user_enable_state").as_str())?,
-            user_type: row.try_get(format!("{prefix}_user_user_type").as_str())?,
-        })
-    }
+fn v1() -> Routes {
+    let admin_only = || axum::middleware::from_fn(admin::require_admin);
+    let log_upload_limit = mutex_lock!(CONFIG).log_upload.max_size as usize;
+    let attachment_limit = mutex_lock!(CONFIG).attachment.max_size as usize;
+    let import_limit = mutex_lock!(CONFIG).import.max_size as usize;
+
+    let mut routes = Routes::default();
+    add_route!(routes, GET "/routes", list_routes);
+    add_route!(routes, GET "/ping", ping::ping);
+    add_route!(routes, GET "/health", health::health);
+    add_route!(routes, GET "/ready", health::ready);
+    add_route!(routes, GET "/metrics", metrics_router::metrics);
+    add_route!(routes, GET "/stage/:stage/devices", device::devices);
+    add_route!(routes, GET "/machine/:code/history", machine::history);
+    add_route!(routes, GET "/users", users::all_users);
+    add_route!(routes, GET "/break/causes", breakpoint::all_break_reasons);
+    add_route!(routes, GET "/break/points", breakpoint::all_breakpoints);
+    add_route!(routes, POST "/inspection", inspection::post_new);
+    add_route!(routes, GET "/inspection/search", inspection::search);
+    add_route!(routes, GET "/inspection/:id/details", inspection::query_details);
+    add_route!(routes, PUT "/inspection/:id", inspection::update);
+    add_route!(routes, GET "/inspection/count", inspection::count);
+    add_route!(routes, POST "/inspection/batch", inspection::batch);
+    add_route!(routes, POST "/inspection/import", inspection::import, DefaultBodyLimit::max(import_limit));
+    add_route!(routes, GET "/inspection/:id/report", report::inspection);
+    add_route!(routes, GET "/report/summary", report::summary);
+    add_route!(routes, POST "/sync/inspections", sync::push);
+    add_route!(routes, GET "/sync/changes", sync::changes);
+    add_route!(routes, POST "/inspection/:id/attachments", attachment::upload, DefaultBodyLimit::max(attachment_limit));
+    add_route!(routes, GET "/inspection/:id/attachments", attachment::list);
+    add_route!(routes, GET "/attachment/:id", attachment::fetch);
+    add_route!(routes, GET "/attachment/:id/thumbnail", attachment::fetch_thumbnail);
+    add_route!(routes, DELETE "/attachment/:id", attachment::delete);
+    add_route!(routes, POST "/log", log_router::upload_log, DefaultBodyLimit::max(log_upload_limit));
+    add_route!(routes, GET "/logs", log_router::list_logs, admin_only());
+    add_route!(routes, GET "/log/:id", log_router::download_log, admin_only());
+    add_route!(routes, DELETE "/log/:id", log_router::delete_log, admin_only());
+    add_route!(routes, GET "/inspection/updatecounter", inspection::update_counter);
+    add_route!(routes, POST "/admin/reload", admin::reload_config, admin_only());
+    add_route!(routes, GET "/openapi.json", openapi::openapi_json);
+    routes
 }
 
-impl Breakpoint {
-    pub fn from_row_prefixed(row: &MySqlRow, prefix: &str) -> sqlx::Result<Self> {
-        Ok(Self {
-            id: row.try_get(format!("{prefix}_bp_id").as_str())?,
-            breakpoint: row.try_get(format!("{prefix}_bp_name").as_str())?,
-            enable_state: row.try_get(format!("{prefix}_bp_enable_state").as_str())?,
-        })
+#[utoipa::path(
+    get, path = "/routes", tag = "meta",
+    responses((status = 200, description = "Routes of each version, under a `# <version>` line, one `<METHOD> <path>` per line", body = String, content_type = "text/plain"))
+)]
+pub async fn list_routes() -> impl IntoResponse {
+    let mut content = String::new();
+    use fmt::Write;
+    for (i, (version, routes)) in mutex_lock!(COLLECTED_ROUTES).iter().enumerate() {
+        if i == 0 {
+            writeln!(&mut content, "# {} (also at /)", version).unwrap();
+        } else {
+            writeln!(&mut content, "\n# {}", version).unwrap();
+        }
+        for (method, path) in routes {
+            writeln!(&mut content, "{} /{}{}", method, version, path).unwrap();
+        }
     }
+    content
 }
 
 pub macro api_error {
@@ -225,5 +187,13 @@ pub macro api_error {
 pub macro handle_errors($r:expr) {{
     log::debug!("Result: {:?}", &$r);
     let err = $r.err().unwrap();
-    api_error!(format!("{}", err))
+    api_error!(crate::handlers::error_message(&err))
 }}
+
+/// Message of an error responded to the client
+pub fn error_message(err: &anyhow::Error) -> String {
+    match err.downcast_ref::<sqlx::Error>() {
+        Some(e) if db::is_unavailable(e) => format!("Database unavailable: {}", e),
+        _ => format!("{}", err),
+    }
+}
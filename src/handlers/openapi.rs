@@ -0,0 +1,58 @@
+use axum::response::IntoResponse;
+use axum::Json;
+use utoipa::OpenApi;
+
+use super::*;
+
+/// The OpenAPI document of all the routes in [`v1`]
+///
+/// Remember to list new handlers here when adding routes.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Break inspection server"),
+    servers((url = "/v1"), (url = "/", description = "Aliases of v1")),
+    paths(
+        list_routes,
+        openapi_json,
+        ping::ping,
+        health::health,
+        health::ready,
+        metrics_router::metrics,
+        device::devices,
+        machine::history,
+        users::all_users,
+        breakpoint::all_break_reasons,
+        breakpoint::all_breakpoints,
+        inspection::post_new,
+        inspection::search,
+        inspection::query_details,
+        inspection::update,
+        inspection::count,
+        inspection::batch,
+        inspection::import,
+        report::inspection,
+        report::summary,
+        sync::push,
+        sync::changes,
+        attachment::upload,
+        attachment::list,
+        attachment::fetch,
+        attachment::fetch_thumbnail,
+        attachment::delete,
+        log_router::upload_log,
+        log_router::list_logs,
+        log_router::download_log,
+        log_router::delete_log,
+        inspection::update_counter,
+        admin::reload_config,
+    )
+)]
+pub struct ApiDoc;
+
+#[utoipa::path(
+    get, path = "/openapi.json", tag = "meta",
+    responses((status = 200, description = "This document", content_type = "application/json"))
+)]
+pub async fn openapi_json() -> impl IntoResponse {
+    Json(ApiDoc::openapi())
+}
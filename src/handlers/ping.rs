@@ -1,22 +1,22 @@
 use axum::extract::Query;
 use axum::response::IntoResponse;
-use log::debug;
-use serde::{Deserialize, Serialize};
+use czttgd_api::Pong;
+use serde::Deserialize;
+use utoipa::IntoParams;
 
 use crate::api_ok;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct Input {
     text: String,
 }
 
-#[derive(Serialize)]
-pub struct Pong {
-    text: String,
-}
-
+#[utoipa::path(
+    get, path = "/ping", tag = "meta", params(Input),
+    responses((status = 200, body = crate::ResponseJson<Pong>))
+)]
 pub async fn ping(query: Query<Input>) -> impl IntoResponse {
-    debug!("Route: ping");
     api_ok!(Pong {
         text: query.text.clone()
     })
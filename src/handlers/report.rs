@@ -0,0 +1,86 @@
+//! PDF reports for printing
+
+use std::collections::HashSet;
+
+use axum::extract::{Path, Query};
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::response::IntoResponse;
+use axum::Extension;
+use chrono::NaiveDate;
+use czttgd_api::SummaryQuery;
+use log::debug;
+
+use crate::handlers::{api_error, handle_errors};
+use crate::report::{self, Font};
+use crate::{mutex_lock, ApiContext, CONFIG};
+
+fn pdf_response(filename: &str, pdf: Vec<u8>) -> axum::response::Response {
+    let disposition = format!("inline; filename=\"{filename}\"");
+    (
+        [
+            (CONTENT_TYPE, "application/pdf".to_string()),
+            (CONTENT_DISPOSITION, disposition),
+        ],
+        pdf,
+    )
+        .into_response()
+}
+
+/// An inspection as a one-page record to sign. Needs `report.font`.
+#[utoipa::path(
+    get, path = "/inspection/{id}/report", tag = "report",
+    params(("id" = i64, Path)),
+    responses((status = 200, description = "The record; the JSON envelope on errors", body = Vec<u8>, content_type = "application/pdf"))
+)]
+pub async fn inspection(
+    Extension(api_context): Extension<ApiContext>,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    let config = mutex_lock!(CONFIG).report.clone();
+
+    let result: anyhow::Result<()> = try {
+        let Some(details) = api_context.inspections.details(id).await? else {
+            return api_error!(format!("No such inspection: {}", id));
+        };
+        let font = Font::load(&config).await?;
+        let pdf = tokio::task::spawn_blocking(move || report::inspection(&details, &font)).await??;
+        return pdf_response(&format!("inspection-{id}.pdf"), pdf);
+    };
+    handle_errors!(result)
+}
+
+/// Inspections of a stage within a period, counted and listed. Needs
+/// `report.font`.
+#[utoipa::path(
+    get, path = "/report/summary", tag = "report", params(SummaryQuery),
+    responses((status = 200, description = "The summary; the JSON envelope on errors", body = Vec<u8>, content_type = "application/pdf"))
+)]
+pub async fn summary(
+    Extension(api_context): Extension<ApiContext>,
+    Query(api_query): Query<SummaryQuery>,
+) -> impl IntoResponse {
+    debug!("Query: {:?}", api_query);
+    let config = mutex_lock!(CONFIG).report.clone();
+
+    let result: anyhow::Result<()> = try {
+        for day in [&api_query.from, &api_query.to] {
+            if NaiveDate::parse_from_str(day, "%Y-%m-%d").is_err() {
+                return api_error!(format!("Invalid date: {}", day));
+            }
+        }
+        let SummaryQuery { stage, from, to } = api_query;
+        let devices = api_context.master_data.devices(stage as i32).await?;
+        let devices = devices.into_iter().collect::<HashSet<_>>();
+        let mut rows = api_context.inspections.export(&from, &to).await?;
+        rows.retain(|x| devices.contains(&x.device_code));
+
+        let font = Font::load(&config).await?;
+        let filename = format!("summary-{stage}-{from}-{to}.pdf");
+        let pdf = tokio::task::spawn_blocking(move || {
+            report::summary(stage, &from, &to, &rows, &font)
+        })
+        .await??;
+        return pdf_response(&filename, pdf);
+    };
+    handle_errors!(result)
+}
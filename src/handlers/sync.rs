@@ -0,0 +1,88 @@
+//! Offline sync for the tablets
+//!
+//! Tablets push the inspections created or edited while offline under
+//! UUIDs of their own, and pull the changes made by others by versions.
+
+use std::collections::HashMap;
+
+use axum::extract::Query;
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use czttgd_api::{Changes, ChangesQuery, SyncItem, SyncResult, SyncStatus};
+use log::debug;
+use uuid::Uuid;
+
+use crate::handlers::inspection::MAX_BATCH_SIZE;
+use crate::handlers::{api_error, handle_errors};
+use crate::{api_ok, metrics, mutex_lock, ApiContext, UPDATE_COUNTER};
+
+/// Applies at most 1000 items in order, all or none. Pushing the same items
+/// again changes nothing, and returns the same ids.
+#[utoipa::path(
+    post, path = "/sync/inspections", tag = "sync",
+    request_body = Vec<SyncItem>,
+    responses((status = 200, description = "One result per item, in order", body = crate::ResponseJson<Vec<SyncResult>>))
+)]
+#[axum::debug_handler]
+pub async fn push(
+    Extension(api_context): Extension<ApiContext>,
+    Json(mut items): Json<Vec<SyncItem>>,
+) -> impl IntoResponse {
+    debug!("Items: {}", items.len());
+
+    let result: anyhow::Result<()> = try {
+        if items.len() > MAX_BATCH_SIZE {
+            return api_error!(format!("Expected at most {} items", MAX_BATCH_SIZE));
+        }
+        for item in &mut items {
+            let Ok(uuid) = Uuid::parse_str(&item.client_id) else {
+                return api_error!(format!("Invalid client id: {}", item.client_id));
+            };
+            item.client_id = uuid.hyphenated().to_string();
+        }
+        let device_codes = items.iter().map(|x| x.form.device_code).collect::<Vec<_>>();
+
+        let results = api_context.sync.push(items).await?;
+
+        // inspections changed per operation and machine
+        let mut machines = HashMap::<(&str, i32), u64>::new();
+        let mut changed = 0;
+        for (result, device_code) in results.iter().zip(device_codes) {
+            let operation = match result.status {
+                SyncStatus::Created => "created",
+                SyncStatus::Updated => "updated",
+                SyncStatus::Unchanged | SyncStatus::Conflict => continue,
+            };
+            *machines.entry((operation, device_code)).or_default() += 1;
+            changed += 1;
+        }
+        *mutex_lock!(UPDATE_COUNTER) += changed;
+        for ((operation, device_code), count) in machines {
+            metrics::record_inspections(&*api_context.master_data, operation, device_code, count)
+                .await;
+        }
+        return api_ok!(results);
+    };
+    handle_errors!(result)
+}
+
+/// Inspections changed after `since`, including the ones soft-deleted
+#[utoipa::path(
+    get, path = "/sync/changes", tag = "sync", params(ChangesQuery),
+    responses((status = 200, body = crate::ResponseJson<Changes>))
+)]
+#[axum::debug_handler]
+pub async fn changes(
+    Extension(api_context): Extension<ApiContext>,
+    Query(api_query): Query<ChangesQuery>,
+) -> impl IntoResponse {
+    debug!("Query: {:?}", api_query);
+
+    let result: anyhow::Result<()> = try {
+        let since = api_query.since.unwrap_or_default();
+        let limit = api_query.limit.unwrap_or(100);
+        let changes = api_context.sync.changes(since, limit).await?;
+        return api_ok!(changes);
+    };
+    handle_errors!(result)
+}
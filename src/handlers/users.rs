@@ -1,18 +1,17 @@
 use axum::response::{IntoResponse, Response};
 use axum::{debug_handler, Extension};
-use futures::TryStreamExt;
-use log::info;
 
 use crate::handlers::{handle_errors, User};
-use crate::{api_ok, include_sql, ApiContext};
+use crate::{api_ok, ApiContext};
 
+#[utoipa::path(
+    get, path = "/users", tag = "master data",
+    responses((status = 200, body = crate::ResponseJson<Vec<User>>))
+)]
 #[debug_handler]
 pub async fn all_users(extension: Extension<ApiContext>) -> Response {
-    info!("Route: /users");
     let result: anyhow::Result<()> = try {
-        let db = &extension.db;
-        let rows = sqlx::query_as::<_, User>(include_sql!("users")).fetch(db);
-        let rows = rows.try_collect::<Vec<_>>().await?;
+        let rows = extension.master_data.users().await?;
         return api_ok!(rows);
     };
     handle_errors!(result)
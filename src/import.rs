@@ -0,0 +1,504 @@
+//! Importing the inspections recorded in spreadsheets before this system
+//!
+//! The first row names the columns, either as in the exported CSV
+//! (`deviceCode`) or as in the old spreadsheets (`机台号`); other names can
+//! be mapped with `map`. Users, break causes and breakpoints are given by
+//! name and resolved to ids.
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use bigdecimal::BigDecimal;
+use calamine::{Data, Reader};
+use chrono::{NaiveDate, NaiveDateTime};
+use czttgd_api::{ImportError, ImportReport};
+
+use crate::handlers::InspectionForm;
+use crate::metrics;
+use crate::repository::{InspectionRepository, MasterDataRepository, TIME_FORMAT};
+
+/// A column of [`InspectionForm`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    Creator,
+    DeviceCode,
+    DeviceCategory,
+    CreationTime,
+    ProductSpec,
+    WireNumber,
+    WireType,
+    BreakSpec,
+    WireBatchCode,
+    StickBatchCode,
+    Warehouse,
+    ProductTime,
+    BreakFlag,
+    BreakpointB,
+    BreakpointA,
+    BreakCauseA,
+    Comments,
+}
+
+impl Field {
+    pub const ALL: [Field; 17] = [
+        Field::Creator,
+        Field::DeviceCode,
+        Field::DeviceCategory,
+        Field::CreationTime,
+        Field::ProductSpec,
+        Field::WireNumber,
+        Field::WireType,
+        Field::BreakSpec,
+        Field::WireBatchCode,
+        Field::StickBatchCode,
+        Field::Warehouse,
+        Field::ProductTime,
+        Field::BreakFlag,
+        Field::BreakpointB,
+        Field::BreakpointA,
+        Field::BreakCauseA,
+        Field::Comments,
+    ];
+
+    /// Column name in the exported CSV
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Creator => "creator",
+            Field::DeviceCode => "deviceCode",
+            Field::DeviceCategory => "deviceCategory",
+            Field::CreationTime => "creationTime",
+            Field::ProductSpec => "productSpec",
+            Field::WireNumber => "wireNum",
+            Field::WireType => "wireType",
+            Field::BreakSpec => "breakSpec",
+            Field::WireBatchCode => "wireBatchCode",
+            Field::StickBatchCode => "stickBatchCode",
+            Field::Warehouse => "warehouse",
+            Field::ProductTime => "productTime",
+            Field::BreakFlag => "breakFlag",
+            Field::BreakpointB => "breakpointB",
+            Field::BreakpointA => "breakpointA",
+            Field::BreakCauseA => "breakCauseA",
+            Field::Comments => "comments",
+        }
+    }
+
+    /// Column name in the old spreadsheets
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::Creator => "创建人",
+            Field::DeviceCode => "机台号",
+            Field::DeviceCategory => "机台类型",
+            Field::CreationTime => "创建时间",
+            Field::ProductSpec => "产品规格",
+            Field::WireNumber => "线数",
+            Field::WireType => "线材类型",
+            Field::BreakSpec => "断线规格",
+            Field::WireBatchCode => "母线批次号",
+            Field::StickBatchCode => "铜杆批次号",
+            Field::Warehouse => "仓库",
+            Field::ProductTime => "生产时间",
+            Field::BreakFlag => "是否拉丝池内断线",
+            Field::BreakpointB => "拉丝池断点",
+            Field::BreakpointA => "断点",
+            Field::BreakCauseA => "初检原因",
+            Field::Comments => "备注",
+        }
+    }
+
+    fn required(self) -> bool {
+        matches!(
+            self,
+            Field::Creator
+                | Field::DeviceCode
+                | Field::DeviceCategory
+                | Field::CreationTime
+                | Field::BreakSpec
+        )
+    }
+}
+
+impl FromStr for Field {
+    type Err = anyhow::Error;
+
+    /// By [`Field::name`] ignoring case, or by [`Field::label`]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("wireNumber") {
+            return Ok(Field::WireNumber);
+        }
+        Field::ALL
+            .into_iter()
+            .find(|x| x.name().eq_ignore_ascii_case(s) || x.label() == s)
+            .ok_or_else(|| anyhow!("Unknown field: {}", s))
+    }
+}
+
+/// Parses `column=field` pairs separated by commas, like
+/// `原因=breakCauseA,班组备注=comments`.
+pub fn parse_map(map: &str) -> anyhow::Result<Vec<(String, Field)>> {
+    map.split(',')
+        .filter(|x| !x.trim().is_empty())
+        .map(|pair| {
+            let Some((column, field)) = pair.split_once('=') else {
+                return Err(anyhow!("Invalid mapping: {}", pair));
+            };
+            Ok((column.trim().to_string(), field.parse()?))
+        })
+        .collect()
+}
+
+/// Rows of cells as text, each with its row number in the file
+type Table = Vec<(usize, Vec<String>)>;
+
+/// Reads the first sheet of an Excel workbook, or else CSV.
+fn read_table(content: Vec<u8>) -> anyhow::Result<Table> {
+    // ZIP of .xlsx and .ods, or OLE of .xls
+    let workbook = content.starts_with(b"PK\x03\x04")
+        || content.starts_with(b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1");
+    if workbook {
+        read_workbook(content)
+    } else {
+        read_csv(&content)
+    }
+}
+
+fn read_workbook(content: Vec<u8>) -> anyhow::Result<Table> {
+    let mut workbook = calamine::open_workbook_auto_from_rs(Cursor::new(content))?;
+    let Some(range) = workbook.worksheet_range_at(0) else {
+        return Err(anyhow!("No sheet"));
+    };
+    let range = range?;
+    let first_row = range.start().map(|x| x.0 as usize).unwrap_or_default();
+    Ok(range
+        .rows()
+        .enumerate()
+        .map(|(i, row)| (first_row + i + 1, row.iter().map(cell_text).collect()))
+        .collect())
+}
+
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Empty => String::new(),
+        // whole numbers are stored as floats
+        Data::Float(x) if x.fract() == 0.0 && x.abs() < 1e15 => format!("{}", *x as i64),
+        Data::DateTime(x) => match x.as_datetime() {
+            Some(time) => time.format(TIME_FORMAT).to_string(),
+            None => x.to_string(),
+        },
+        x => x.to_string(),
+    }
+}
+
+fn read_csv(content: &[u8]) -> anyhow::Result<Table> {
+    // Excel writes UTF-8 CSV with a BOM
+    let content = content.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(content);
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content);
+    let mut table = vec![];
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map(|x| x.line()).unwrap_or_default() as usize;
+        table.push((line, record.iter().map(String::from).collect()));
+    }
+    Ok(table)
+}
+
+/// Normalizes the common ways of writing a time into [`TIME_FORMAT`].
+fn parse_time(text: &str) -> Option<String> {
+    const FORMATS: &[&str] = &[
+        TIME_FORMAT,
+        "%Y-%m-%d %H:%M",
+        "%Y/%m/%d %H:%M:%S",
+        "%Y/%m/%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S%.f",
+    ];
+    let time = FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(text, f).ok())
+        .or_else(|| {
+            ["%Y-%m-%d", "%Y/%m/%d"]
+                .iter()
+                .find_map(|f| NaiveDate::parse_from_str(text, f).ok())
+                .and_then(|x| x.and_hms_opt(0, 0, 0))
+        })?;
+    Some(time.format(TIME_FORMAT).to_string())
+}
+
+fn parse_bool(text: &str) -> Option<bool> {
+    match text.to_lowercase().as_str() {
+        "" | "0" | "false" | "否" | "n" | "no" => Some(false),
+        "1" | "true" | "是" | "y" | "yes" => Some(true),
+        _ => None,
+    }
+}
+
+/// Ids by name
+struct Names(HashMap<String, Vec<i32>>);
+
+impl Names {
+    fn new(names: impl Iterator<Item = (Option<String>, i32)>) -> Self {
+        let mut map = HashMap::<_, Vec<_>>::new();
+        for (name, id) in names {
+            if let Some(name) = name {
+                map.entry(name.trim().to_string()).or_default().push(id);
+            }
+        }
+        Self(map)
+    }
+
+    fn resolve(&self, name: &str) -> Result<i32, String> {
+        match self.0.get(name).map(|x| x.as_slice()) {
+            Some(&[id]) => Ok(id),
+            Some([_, ..]) => Err(format!("Ambiguous name: {}", name)),
+            _ => Err(format!("No such name: {}", name)),
+        }
+    }
+}
+
+/// Master data for resolving the rows
+struct References {
+    users: Names,
+    break_causes: Names,
+    breakpoints: Names,
+    /// Machines looked up so far, and whether they exist
+    machines: HashMap<i32, bool>,
+}
+
+impl References {
+    async fn load(master_data: &dyn MasterDataRepository) -> anyhow::Result<Self> {
+        let users = master_data.users().await?;
+        let break_causes = master_data.break_causes().await?;
+        let breakpoints = master_data.breakpoints().await?;
+        Ok(Self {
+            users: Names::new(users.into_iter().map(|x| (Some(x.name), x.id))),
+            break_causes: Names::new(break_causes.into_iter().map(|x| (x.cause, x.id))),
+            breakpoints: Names::new(breakpoints.into_iter().map(|x| (x.breakpoint, x.id))),
+            machines: Default::default(),
+        })
+    }
+}
+
+/// The cells of a row by field, collecting the errors
+struct Row<'a> {
+    number: usize,
+    cells: &'a [String],
+    columns: &'a HashMap<Field, (usize, String)>,
+    errors: Vec<ImportError>,
+}
+
+impl Row<'_> {
+    fn error(&mut self, field: Field, message: String) {
+        self.errors.push(ImportError {
+            row: self.number,
+            column: self.columns.get(&field).map(|x| x.1.clone()),
+            message,
+        });
+    }
+
+    fn text(&self, field: Field) -> Option<String> {
+        let (index, _) = self.columns.get(&field)?;
+        let text = self.cells.get(*index)?.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    fn required(&mut self, field: Field) -> String {
+        let text = self.text(field);
+        if text.is_none() {
+            self.error(field, "Required".into());
+        }
+        text.unwrap_or_default()
+    }
+
+    fn parse<T>(
+        &mut self,
+        field: Field,
+        parse: impl FnOnce(&str) -> Result<T, String>,
+    ) -> Option<T> {
+        let text = self.text(field)?;
+        parse(&text).map_err(|e| self.error(field, e)).ok()
+    }
+}
+
+fn parse_int(text: &str) -> Result<i32, String> {
+    text.parse().map_err(|_| format!("Not an integer: {}", text))
+}
+
+async fn form_of(
+    row: &mut Row<'_>,
+    references: &mut References,
+    master_data: &dyn MasterDataRepository,
+) -> anyhow::Result<InspectionForm> {
+    let creator = row.required(Field::Creator);
+    let creator = match creator.is_empty() {
+        true => None,
+        false => references
+            .users
+            .resolve(&creator)
+            .map_err(|e| row.error(Field::Creator, e))
+            .ok(),
+    };
+    let device_code = row.parse(Field::DeviceCode, parse_int);
+    match device_code {
+        Some(code) => {
+            let exists = match references.machines.get(&code) {
+                Some(&exists) => exists,
+                None => {
+                    let exists = master_data.machine_stage(code).await?.is_some();
+                    references.machines.insert(code, exists);
+                    exists
+                }
+            };
+            if !exists {
+                row.error(Field::DeviceCode, format!("No such machine: {}", code));
+            }
+        }
+        None if row.text(Field::DeviceCode).is_none() => {
+            row.required(Field::DeviceCode);
+        }
+        None => {}
+    }
+    let device_category = row.required(Field::DeviceCategory);
+    let creation_time = row.required(Field::CreationTime);
+    let creation_time = match creation_time.is_empty() {
+        true => String::new(),
+        false => parse_time(&creation_time).unwrap_or_else(|| {
+            row.error(Field::CreationTime, format!("Invalid time: {}", creation_time));
+            creation_time
+        }),
+    };
+    let break_spec = row.required(Field::BreakSpec);
+    let break_flag = row
+        .parse(Field::BreakFlag, |x| {
+            parse_bool(x).ok_or_else(|| format!("Not a yes or no: {}", x))
+        })
+        .unwrap_or_default();
+    let breakpoint_b = row.parse(Field::BreakpointB, |x| {
+        x.parse::<BigDecimal>()
+            .map(|_| x.to_string())
+            .map_err(|_| format!("Not a number: {}", x))
+    });
+
+    Ok(InspectionForm {
+        creator: creator.unwrap_or_default(),
+        device_code: device_code.unwrap_or_default(),
+        device_category,
+        creation_time,
+        product_spec: row.text(Field::ProductSpec),
+        wire_number: row.parse(Field::WireNumber, parse_int),
+        wire_type: row.text(Field::WireType),
+        break_spec,
+        wire_batch_code: row.text(Field::WireBatchCode),
+        stick_batch_code: row.text(Field::StickBatchCode),
+        warehouse: row.text(Field::Warehouse),
+        break_flag,
+        breakpoint_b,
+        breakpoint_a: row.parse(Field::BreakpointA, |x| references.breakpoints.resolve(x)),
+        break_cause_a: row.parse(Field::BreakCauseA, |x| references.break_causes.resolve(x)),
+        comments: row.text(Field::Comments),
+        product_time: row.text(Field::ProductTime),
+    })
+}
+
+/// Reads a CSV file or an Excel workbook, and inserts its rows as
+/// inspections in one transaction. Nothing is imported if any row has
+/// errors; a dry run reports them without importing anything either.
+///
+/// Fails only if the file can't be read.
+pub async fn import(
+    content: Vec<u8>,
+    map: &[(String, Field)],
+    dry_run: bool,
+    inspections: &dyn InspectionRepository,
+    master_data: &dyn MasterDataRepository,
+) -> anyhow::Result<ImportReport> {
+    let mut table = tokio::task::spawn_blocking(move || read_table(content))
+        .await?
+        .map_err(|e| anyhow!("Unreadable file: {}", e))?;
+    let mut report = ImportReport {
+        dry_run,
+        ..Default::default()
+    };
+    if table.is_empty() {
+        return Ok(report);
+    }
+    let (header_row, header) = table.remove(0);
+
+    let mut columns = HashMap::new();
+    for (index, name) in header.iter().enumerate() {
+        let name = name.trim();
+        let field = match map.iter().find(|x| x.0 == name) {
+            Some(&(_, field)) => Some(field),
+            None => name.parse::<Field>().ok(),
+        };
+        if let Some(field) = field {
+            columns.entry(field).or_insert((index, name.to_string()));
+        }
+    }
+    for field in Field::ALL {
+        if field.required() && !columns.contains_key(&field) {
+            report.errors.push(ImportError {
+                row: header_row,
+                column: None,
+                message: format!("Missing column: {} ({})", field.label(), field.name()),
+            });
+        }
+    }
+    if !report.errors.is_empty() {
+        return Ok(report);
+    }
+
+    let mut references = References::load(master_data).await?;
+    let mut rows = vec![];
+    let mut forms = vec![];
+    for (number, cells) in &table {
+        if cells.iter().all(|x| x.trim().is_empty()) {
+            continue;
+        }
+        let mut row = Row {
+            number: *number,
+            cells,
+            columns: &columns,
+            errors: vec![],
+        };
+        let form = form_of(&mut row, &mut references, master_data).await?;
+        report.errors.append(&mut row.errors);
+        rows.push(*number);
+        forms.push(form);
+    }
+    report.rows = forms.len();
+    if !report.errors.is_empty() || forms.is_empty() {
+        return Ok(report);
+    }
+
+    let mut machines = HashMap::<i32, u64>::new();
+    for form in &forms {
+        *machines.entry(form.device_code).or_default() += 1;
+    }
+    let results = inspections.import(forms, dry_run).await?;
+    for (number, result) in rows.into_iter().zip(results) {
+        match result {
+            Ok(id) => report.ids.push(id),
+            Err(message) => report.errors.push(ImportError {
+                row: number,
+                column: None,
+                message,
+            }),
+        }
+    }
+    if report.errors.is_empty() {
+        report.imported = report.ids.len();
+    }
+    if dry_run || !report.errors.is_empty() {
+        report.ids.clear();
+    } else {
+        for (device_code, count) in machines {
+            metrics::record_inspections(master_data, "created", device_code, count).await;
+        }
+    }
+    Ok(report)
+}
@@ -2,21 +2,34 @@
 #![feature(decl_macro)]
 #![feature(yeet_expr)]
 
-use std::io;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::config::Config;
+use crate::db::Database;
+use crate::repository::{
+    AttachmentRepository, InspectionRepository, MasterDataRepository, SyncRepository,
+};
 use axum::response::IntoResponse;
-use axum::Json;
+use object_store::ObjectStore;
 use once_cell::sync::Lazy;
-use serde::Serialize;
-use sqlx::mysql::MySqlRow;
-use sqlx::{FromRow, MySql, Pool, Row, ValueRef};
+use sqlx::{ColumnIndex, MySql, Pool, Row, ValueRef};
 
+pub mod attachment;
+pub mod cli;
 pub mod config;
+pub mod db;
 pub mod handlers;
+pub mod import;
+pub mod logging;
+pub mod metrics;
+pub mod reload;
+pub mod report;
+pub mod repository;
+pub mod summary;
+
+pub use czttgd_api::{Empty, RefId, ResponseJson};
 
 pub const DATABASE_NAME: &str = "breakInfo";
 
@@ -27,75 +40,129 @@ macro lazy_default() {
 pub static ARGS: Lazy<Mutex<Args>> = lazy_default!();
 pub static CONFIG: Lazy<Mutex<Config>> = lazy_default!();
 
+/// Command-line arguments
+///
+/// Options here override the ones in the config file and environment
+/// variables (`CZTTGD_*`).
 #[derive(clap::Parser, Debug, Default, Clone)]
 pub struct Args {
     #[arg(default_value = "./server.toml")]
     pub config: PathBuf,
+    /// Print the effective config with secrets redacted, and exit
+    #[arg(long)]
+    pub print_config: bool,
+    #[arg(long)]
+    pub listen_port: Option<u16>,
+    #[arg(long)]
+    pub mysql_url: Option<String>,
+    #[arg(long)]
+    pub mysql_ip: Option<String>,
+    #[arg(long)]
+    pub mysql_port: Option<u16>,
+    #[arg(long)]
+    pub mysql_username: Option<String>,
+    #[arg(long)]
+    pub mysql_password: Option<String>,
+    #[arg(long)]
+    pub mysql_database: Option<String>,
+    #[arg(long)]
+    pub log_file: Option<String>,
+    /// Runs the server if not given
+    #[command(subcommand)]
+    pub command: Option<Command>,
 }
 
-pub fn set_up_logging(file: impl AsRef<Path>) -> anyhow::Result<()> {
-    fern::Dispatch::new()
-        .format(|out, message, record| {
-            out.finish(format_args!(
-                "[{} {} {}] {}",
-                humantime::format_rfc3339(std::time::SystemTime::now()),
-                record.level(),
-                record.target(),
-                message
-            ))
-        })
-        .level(log::LevelFilter::Debug)
-        .chain(io::stdout())
-        .chain(fern::log_file(file)?)
-        .apply()?;
-    Ok(())
+/// Operational tasks on the same database as the server
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Show the `tt_number` serial used in new inspection ids, or change it
+    Serial {
+        /// The new value
+        value: Option<i32>,
+        /// Reset it to 1
+        #[arg(long, conflicts_with = "value")]
+        reset: bool,
+    },
+    /// Print an inspection as JSON
+    Inspection { id: i64 },
+    /// Soft-delete an inspection
+    Delete { id: i64 },
+    /// Restore a soft-deleted inspection
+    Restore { id: i64 },
+    /// Enable a user, break cause or breakpoint
+    Enable { kind: RefKind, id: RefId },
+    /// Disable a user, break cause or breakpoint
+    Disable { kind: RefKind, id: RefId },
+    /// Apply pending migrations
+    Migrate,
+    /// Export the inspections created within a date range to CSV
+    Export {
+        /// First day, like `2024-01-01`
+        #[arg(long)]
+        from: String,
+        /// Last day, inclusive
+        #[arg(long)]
+        to: String,
+        /// Writes to stdout if not given
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Import inspections from a CSV file or an Excel workbook; nothing is
+    /// imported if any row has errors
+    Import {
+        file: PathBuf,
+        /// Check the rows without importing them
+        #[arg(long)]
+        dry_run: bool,
+        /// Extra column names, like `原因=breakCauseA,班组备注=comments`
+        #[arg(long)]
+        map: Option<String>,
+    },
+    /// Check the config and the database connectivity
+    Check,
+    /// Copy all the data into the empty database of another config file,
+    /// e.g. from MySQL to SQLite; attachment objects are not copied
+    Copy {
+        /// Config file of the target; only its `backend`, `mysql` and
+        /// `sqlite` are used
+        config: PathBuf,
+    },
 }
 
-#[derive(Serialize)]
-pub struct ResponseJson<D: Serialize> {
-    data: Option<D>,
-    code: u32,
-    message: Option<String>,
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum RefKind {
+    User,
+    Cause,
+    Breakpoint,
 }
 
-impl<D: Serialize> ResponseJson<D> {
-    pub fn ok(data: D) -> Self {
-        Self {
-            data: Some(data),
-            code: 0,
-            message: None,
-        }
-    }
+pub type MySqlPool = Pool<MySql>;
 
-    pub fn error() -> Self {
-        Self {
-            data: None,
-            code: 1,
-            message: None,
-        }
-    }
+pub struct ApiContextInner {
+    pub db: Database,
+    pub inspections: Arc<dyn InspectionRepository>,
+    pub master_data: Arc<dyn MasterDataRepository>,
+    pub attachments: Arc<dyn AttachmentRepository>,
+    pub sync: Arc<dyn SyncRepository>,
+    /// Where inspection photos are stored
+    pub attachment_store: Box<dyn ObjectStore>,
+}
 
-    pub fn error_msg<S: Into<String>>(message: S) -> Self {
+impl ApiContextInner {
+    /// With the repositories over `db`
+    pub fn new(db: Database, attachment_store: Box<dyn ObjectStore>) -> Self {
+        let repositories = db.repositories();
         Self {
-            data: None,
-            code: 1,
-            message: Some(message.into()),
+            db,
+            inspections: repositories.inspections,
+            master_data: repositories.master_data,
+            attachments: repositories.attachments,
+            sync: repositories.sync,
+            attachment_store,
         }
     }
 }
 
-impl<D: Serialize> IntoResponse for ResponseJson<D> {
-    fn into_response(self) -> axum::response::Response {
-        Json(self).into_response()
-    }
-}
-
-pub type MySqlPool = Pool<MySql>;
-
-pub struct ApiContextInner {
-    pub db: MySqlPool,
-}
-
 pub type ApiContext = Arc<ApiContextInner>;
 
 pub macro mutex_lock($e:expr) {
@@ -106,10 +173,11 @@ pub macro api_ok($d:expr) {
     crate::ResponseJson::ok($d).into_response()
 }
 
-fn check_from_row<'a, T, F>(r: &'a MySqlRow, ref_name: &str, get_fn: F) -> sqlx::Result<Option<T>>
+fn check_from_row<'a, R, T, F>(r: &'a R, ref_name: &str, get_fn: F) -> sqlx::Result<Option<T>>
 where
-    T: FromRow<'a, MySqlRow>,
-    F: FnOnce(&'a MySqlRow) -> sqlx::Result<T>,
+    R: Row,
+    for<'n> &'n str: ColumnIndex<R>,
+    F: FnOnce(&'a R) -> sqlx::Result<T>,
 {
     try {
         if !r.try_get_raw(ref_name)?.is_null() {
@@ -124,9 +192,6 @@ pub macro include_sql($name:literal) {
     include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/sqls/", $name, ".sql"))
 }
 
-// their SQL uses `INT` but not `INT UNSIGNED`
-pub type RefId = i32;
-
 pub fn timestamp_secs() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -0,0 +1,278 @@
+use std::fs::{rename, File};
+use std::io;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime};
+
+use axum::body::HttpBody;
+use axum::extract::{ConnectInfo, MatchedPath, Request};
+use axum::http::header::CONTENT_LENGTH;
+use axum::http::{HeaderMap, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+use log::{info, Metadata, Record};
+use once_cell::sync::Lazy;
+
+use crate::config::{LogFormat, Logging, Rotation};
+use crate::mutex_lock;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    /// ID of the request being handled by the current task
+    pub static REQUEST_ID: String;
+}
+
+/// Logging config in effect; replaced on config reloads
+static LOGGING: Lazy<Mutex<Logging>> = Lazy::new(|| Mutex::new(Default::default()));
+
+/// Target of the access log lines written by [`access_log`]
+pub const ACCESS_LOG_TARGET: &str = "access";
+
+/// The log file currently written to
+static LOG_FILE: Lazy<Mutex<Option<LogFile>>> = Lazy::new(|| Mutex::new(None));
+/// The access log file currently written to
+static ACCESS_LOG_FILE: Lazy<Mutex<Option<LogFile>>> = Lazy::new(|| Mutex::new(None));
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = fern::log_file(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    /// Renames `<file>` to `<file>.1`, `<file>.1` to `<file>.2` and so on,
+    /// keeping at most `max_files` old files, then starts a new one.
+    fn rotate(&mut self, max_files: u32) -> io::Result<()> {
+        let numbered = |n: u32| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{n}"));
+            PathBuf::from(name)
+        };
+        if max_files > 0 {
+            for n in (1..max_files).rev() {
+                let from = numbered(n);
+                if from.exists() {
+                    rename(from, numbered(n + 1))?;
+                }
+            }
+            rename(&self.path, numbered(1))?;
+        } else {
+            File::create(&self.path)?;
+        }
+        *self = Self::open(&self.path)?;
+        Ok(())
+    }
+
+    fn write(&mut self, buf: &[u8], rotation: Option<&Rotation>) -> io::Result<usize> {
+        if let Some(r) = rotation {
+            if self.size > 0 && self.size + buf.len() as u64 > r.max_size {
+                self.rotate(r.max_files)?;
+            }
+        }
+        let n = self.file.write(buf)?;
+        self.size += n as u64;
+        Ok(n)
+    }
+}
+
+struct LogFileWriter(&'static Mutex<Option<LogFile>>);
+
+impl Write for LogFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let rotation = mutex_lock!(LOGGING).rotation.clone();
+        match &mut *mutex_lock!(self.0) {
+            Some(f) => f.write(buf, rotation.as_ref()),
+            None => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut *mutex_lock!(self.0) {
+            Some(f) => f.file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Whether access log lines have their own file
+fn separate_access_log() -> bool {
+    mutex_lock!(LOGGING).access_file.is_some()
+}
+
+fn enabled(metadata: &Metadata) -> bool {
+    let logging = mutex_lock!(LOGGING);
+    let target = metadata.target();
+    // the most specific module wins
+    let level = logging
+        .modules
+        .iter()
+        .filter(|(m, _)| {
+            target == m.as_str()
+                || (target.starts_with(m.as_str()) && target[m.len()..].starts_with("::"))
+        })
+        .max_by_key(|(m, _)| m.len())
+        .map(|(_, l)| *l)
+        .unwrap_or(logging.level);
+    metadata.level() <= level
+}
+
+fn format(out: fern::FormatCallback, message: &std::fmt::Arguments, record: &Record) {
+    let time = humantime::format_rfc3339(SystemTime::now());
+    let request_id = REQUEST_ID.try_with(|x| x.clone()).ok();
+    let format = mutex_lock!(LOGGING).format;
+    match format {
+        LogFormat::Text => match request_id {
+            Some(id) => out.finish(format_args!(
+                "[{} {} {} {}] {}",
+                time,
+                record.level(),
+                record.target(),
+                id,
+                message
+            )),
+            None => out.finish(format_args!(
+                "[{} {} {}] {}",
+                time,
+                record.level(),
+                record.target(),
+                message
+            )),
+        },
+        LogFormat::Json => {
+            let json = serde_json::json!({
+                "time": time.to_string(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "requestId": request_id,
+                "message": message.to_string(),
+            });
+            out.finish(format_args!("{}", json))
+        }
+    }
+}
+
+/// Logs to stdout, and also to the configured file if any. Access log
+/// lines go to their own file instead if configured.
+///
+/// The config can be changed later with [`apply_config`].
+pub fn set_up_logging(config: Logging) -> anyhow::Result<()> {
+    let general = fern::Dispatch::new()
+        .filter(|m| m.target() != ACCESS_LOG_TARGET || !separate_access_log())
+        .chain(io::stdout())
+        .chain(fern::Output::writer(
+            Box::new(LogFileWriter(&LOG_FILE)),
+            "\n",
+        ));
+    let access = fern::Dispatch::new()
+        .filter(|m| m.target() == ACCESS_LOG_TARGET && separate_access_log())
+        .chain(fern::Output::writer(
+            Box::new(LogFileWriter(&ACCESS_LOG_FILE)),
+            "\n",
+        ));
+    fern::Dispatch::new()
+        .format(format)
+        .filter(enabled)
+        .chain(general)
+        .chain(access)
+        .apply()?;
+    apply_config(config)?;
+    Ok(())
+}
+
+fn switch_file(log_file: &Mutex<Option<LogFile>>, path: Option<&String>) -> io::Result<()> {
+    let mut log_file = mutex_lock!(log_file);
+    let current = log_file.as_ref().map(|x| x.path.as_path());
+    if path.map(Path::new) != current {
+        *log_file = match path {
+            Some(f) => Some(LogFile::open(f)?),
+            None => None,
+        };
+    }
+    Ok(())
+}
+
+pub fn apply_config(config: Logging) -> io::Result<()> {
+    switch_file(&LOG_FILE, config.file.as_ref())?;
+    switch_file(&ACCESS_LOG_FILE, config.access_file.as_ref())?;
+    let max_level = config
+        .modules
+        .values()
+        .copied()
+        .fold(config.level, Ord::max);
+    log::set_max_level(max_level);
+    *mutex_lock!(LOGGING) = config;
+    Ok(())
+}
+
+/// Tags the request with an ID, from the `X-Request-Id` header or a newly
+/// generated one, which is included in all its log lines and echoed in the
+/// response.
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|x| x.to_str().ok())
+        .filter(|x| !x.is_empty() && x.len() <= 64)
+        .map(String::from)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().simple().to_string());
+
+    let mut response = REQUEST_ID.scope(id.clone(), next.run(request)).await;
+    if let Ok(v) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, v);
+    }
+    response
+}
+
+/// Writes an access log line for every request, with the client address,
+/// method, URI, matched route, status, body sizes and latency.
+pub async fn access_log(request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let client = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|x| x.0.to_string())
+        .unwrap_or_else(|| "-".into());
+    let method = request.method().clone();
+    let uri = request.uri().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|x| x.as_str().to_string())
+        .unwrap_or_else(|| "-".into());
+    let request_size = content_length(request.headers());
+
+    let response = next.run(request).await;
+
+    let response_size = content_length(response.headers())
+        .or_else(|| response.body().size_hint().exact().map(|x| x.to_string()));
+    info!(
+        target: ACCESS_LOG_TARGET,
+        "{} \"{} {}\" {} {} {} {} {}",
+        client,
+        method,
+        uri,
+        route,
+        response.status().as_u16(),
+        request_size.as_deref().unwrap_or("-"),
+        response_size.as_deref().unwrap_or("-"),
+        humantime::format_duration(start.elapsed())
+    );
+    response
+}
+
+fn content_length(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(CONTENT_LENGTH)
+        .and_then(|x| x.to_str().ok())
+        .map(String::from)
+}
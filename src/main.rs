@@ -2,57 +2,60 @@
 
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::{Extension, Form, Router};
 use axum::extract::DefaultBodyLimit;
+use axum::middleware;
 use axum::routing::get;
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
 use clap::Parser;
+use futures::future::try_join_all;
 use log::{debug, info};
-use sqlx::MySqlPool;
 
 use czttgd_dao::{
-    ApiContext, ApiContextInner, Args, ARGS, CONFIG, DATABASE_NAME, handlers, mutex_lock,
-    set_up_logging,
+    attachment, cli, ApiContext, ApiContextInner, Args, ARGS, CONFIG, handlers, logging, metrics, mutex_lock,
 };
-use czttgd_dao::config::get_config;
+use czttgd_dao::config::{get_config, Backend};
+use czttgd_dao::db::Database;
+use czttgd_dao::logging::set_up_logging;
+use czttgd_dao::reload;
+use czttgd_dao::reload::TLS_CONFIG;
+use czttgd_dao::summary;
 use czttgd_dao::handlers::{inspection, InspectionForm};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let args = Args::parse();
-    let config = get_config(&args.config)?;
-    if let Some(l) = &config.logging
-        && let Some(f) = &l.file
-    {
-        set_up_logging(f)?;
+    let config = get_config(&args)?;
+    if args.print_config {
+        print!("{}", toml::to_string_pretty(&config.redacted())?);
+        return Ok(());
     }
-    debug!("Args: {:?}", args);
-    debug!("Configs: {:?}", config);
+    if let Some(command) = args.command.clone() {
+        return cli::run(command, &config).await;
+    }
+    set_up_logging(config.logging.clone().unwrap_or_default())?;
+    debug!("Args: {:?}", args.redacted());
+    debug!("Configs: {:?}", config.redacted());
     *mutex_lock!(ARGS) = args.clone();
     *mutex_lock!(CONFIG) = config.clone();
 
+    for schedule in &config.summary.schedule {
+        summary::parse_schedule(schedule)?;
+    }
+
     info!("Connecting to the database...");
-    let pool = MySqlPool::connect(
-        format!(
-            "mysql://{}:{}@{}:{}/{}",
-            config.mysql.username,
-            config.mysql.password,
-            config.mysql.ip,
-            config.mysql.port,
-            DATABASE_NAME
-        )
-        .as_str(),
-    )
-    .await?;
-    info!("Testing the connection...");
-    let row: (i64,) = sqlx::query_as("SELECT ?")
-        .bind(42_i64)
-        .fetch_one(&pool)
-        .await?;
-    assert_eq!(row.0, 42);
+    let db = Database::connect(&config).await?;
     info!("Done.");
+    if db.backend() == Backend::MySql && config.mysql.migrate_on_startup {
+        db.migrate().await?;
+    }
+
+    let attachment_store = attachment::open_store(&config.attachment)?;
 
-    start_axum(Arc::new(ApiContextInner { db: pool })).await?;
+    start_axum(Arc::new(ApiContextInner::new(db, attachment_store))).await?;
 
     Ok(())
 }
@@ -62,15 +65,89 @@ fn router() -> Router {
 }
 
 async fn start_axum(api_context: ApiContext) -> anyhow::Result<()> {
-    let listen_port = mutex_lock!(CONFIG).listen_port;
+    let config = mutex_lock!(CONFIG).clone();
 
-    let addr = SocketAddr::new("0.0.0.0".parse().unwrap(), listen_port);
-
-    let listener = tokio::net::TcpListener::bind(addr).await?;
     let router = router()
-        .layer(Extension(api_context))
-        .layer(DefaultBodyLimit::max(1048576 * 50));
-    info!("Server started on {}", addr);
-    axum::serve(listener, router).await?;
+        .layer(Extension(Arc::clone(&api_context)))
+        .layer(DefaultBodyLimit::max(config.max_body_size as usize))
+        .layer(middleware::from_fn(metrics::track))
+        .layer(middleware::from_fn(logging::access_log))
+        .layer(middleware::from_fn(logging::request_id));
+
+    let tls_config = match &config.tls {
+        Some(tls) => Some(RustlsConfig::from_pem_file(&tls.cert, &tls.key).await?),
+        None => None,
+    };
+    *mutex_lock!(TLS_CONFIG) = tls_config.clone();
+    let scheme = if tls_config.is_some() { "https" } else { "http" };
+
+    let mut handles = Vec::new();
+    let mut servers = Vec::new();
+    for ip in &config.listen_addresses {
+        let addr = SocketAddr::new(*ip, config.listen_port);
+        let handle = Handle::new();
+        let service = router
+            .clone()
+            .into_make_service_with_connect_info::<SocketAddr>();
+        let server = match &tls_config {
+            Some(tls_config) => tokio::spawn(
+                axum_server::bind_rustls(addr, tls_config.clone())
+                    .handle(handle.clone())
+                    .serve(service),
+            ),
+            None => tokio::spawn(axum_server::bind(addr).handle(handle.clone()).serve(service)),
+        };
+        info!("Server started on {}://{}", scheme, addr);
+        handles.push(handle);
+        servers.push(async move { anyhow::Ok(server.await??) });
+    }
+
+    #[cfg(unix)]
+    tokio::spawn(reload::reload_on_sighup());
+    tokio::spawn(summary::run_schedule(Arc::clone(&api_context)));
+    tokio::spawn(handlers::run_log_retention());
+
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        // may have been changed by a reload
+        let shutdown_timeout = Duration::from_secs(mutex_lock!(CONFIG).shutdown_timeout);
+        info!(
+            "Shutting down; waiting at most {} for in-flight requests...",
+            humantime::format_duration(shutdown_timeout)
+        );
+        for handle in handles {
+            handle.graceful_shutdown(Some(shutdown_timeout));
+        }
+    });
+
+    try_join_all(servers).await?;
+
+    info!("Closing the database...");
+    api_context.db.close().await;
+    info!("Server stopped.");
     Ok(())
 }
+
+/// Resolves on SIGINT or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for SIGINT");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        signal(SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
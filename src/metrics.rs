@@ -0,0 +1,144 @@
+//! Prometheus metrics, served at `/metrics`
+
+use std::time::Instant;
+
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use log::warn;
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_histogram, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    Encoder, Histogram, HistogramVec, IntCounterVec, IntGauge, TextEncoder,
+};
+
+use crate::db::Database;
+use crate::repository::MasterDataRepository;
+use crate::{mutex_lock, UPDATE_COUNTER};
+
+pub static HTTP_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "czttgd_http_requests_total",
+        "Number of HTTP requests",
+        &["method", "route", "status"]
+    )
+    .unwrap()
+});
+
+pub static HTTP_REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "czttgd_http_request_duration_seconds",
+        "HTTP request latencies",
+        &["method", "route"]
+    )
+    .unwrap()
+});
+
+/// Labeled by operation (`created`, `updated` or `deleted`) and stage
+pub static INSPECTIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "czttgd_inspections_total",
+        "Number of inspection changes",
+        &["operation", "stage"]
+    )
+    .unwrap()
+});
+
+pub static LOG_UPLOAD_BYTES: Lazy<Histogram> = Lazy::new(|| {
+    register_histogram!(
+        "czttgd_log_upload_bytes",
+        "Sizes of uploaded client logs",
+        prometheus::exponential_buckets(1024.0, 4.0, 10).unwrap()
+    )
+    .unwrap()
+});
+
+static UPDATE_COUNTER_GAUGE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "czttgd_update_counter",
+        "Current value of the update counter"
+    )
+    .unwrap()
+});
+
+static DB_POOL_SIZE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("czttgd_db_pool_connections", "Open database connections").unwrap()
+});
+
+static DB_POOL_IDLE: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!(
+        "czttgd_db_pool_idle_connections",
+        "Idle database connections"
+    )
+    .unwrap()
+});
+
+static DB_POOL_MAX: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("czttgd_db_pool_max_connections", "Max database connections").unwrap()
+});
+
+/// Counts the request and records its latency, labeled by the matched route.
+pub async fn track(request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = request.method().to_string();
+    // raw paths would make the label set unbounded
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|x| x.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".into());
+
+    let response = next.run(request).await;
+
+    HTTP_REQUESTS
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[&method, &route])
+        .observe(start.elapsed().as_secs_f64());
+    response
+}
+
+/// Counts an inspection change, labeled by the stage of its machine.
+pub async fn record_inspection(
+    master_data: &dyn MasterDataRepository,
+    operation: &str,
+    device_code: i32,
+) {
+    record_inspections(master_data, operation, device_code, 1).await
+}
+
+/// Like [`record_inspection`], for `count` changes on one machine
+pub async fn record_inspections(
+    master_data: &dyn MasterDataRepository,
+    operation: &str,
+    device_code: i32,
+    count: u64,
+) {
+    let stage = master_data.machine_stage(device_code).await;
+    let stage = match stage {
+        Ok(Some(s)) => s.to_string(),
+        Ok(None) => "unknown".into(),
+        Err(e) => {
+            warn!(
+                "Failed to query the stage of machine {}: {}",
+                device_code, e
+            );
+            "unknown".into()
+        }
+    };
+    INSPECTIONS.with_label_values(&[operation, &stage]).inc_by(count);
+}
+
+/// All metrics in the Prometheus text format
+pub fn gather(db: &Database) -> anyhow::Result<String> {
+    UPDATE_COUNTER_GAUGE.set(*mutex_lock!(UPDATE_COUNTER) as i64);
+    let (size, idle, max) = db.pool_stats();
+    DB_POOL_SIZE.set(size as i64);
+    DB_POOL_IDLE.set(idle as i64);
+    DB_POOL_MAX.set(max as i64);
+
+    let mut buf = Vec::new();
+    TextEncoder::new().encode(&prometheus::gather(), &mut buf)?;
+    Ok(String::from_utf8(buf)?)
+}
@@ -0,0 +1,113 @@
+//! Reloading the config at runtime, on SIGHUP or through `POST /admin/reload`
+
+use std::sync::Mutex;
+
+use axum_server::tls_rustls::RustlsConfig;
+use czttgd_api::ReloadReport;
+use log::{error, info};
+use once_cell::sync::Lazy;
+
+use crate::config::get_config;
+use crate::{logging, mutex_lock, summary, ARGS, CONFIG};
+
+/// TLS config shared by the listeners, if serving HTTPS
+pub static TLS_CONFIG: Lazy<Mutex<Option<RustlsConfig>>> =
+    Lazy::new(|| Mutex::new(Default::default()));
+
+/// Serializes concurrent reloads
+static RELOAD_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Re-reads the config file, environment variables and the original
+/// command-line arguments, and applies what can change at runtime.
+///
+/// Settings that need a restart keep their old values in [`CONFIG`], so
+/// it always reflects the config actually in effect.
+pub async fn reload() -> anyhow::Result<ReloadReport> {
+    let _guard = RELOAD_LOCK.lock().await;
+
+    let args = mutex_lock!(ARGS).clone();
+    let old = mutex_lock!(CONFIG).clone();
+    let mut new = get_config(&args)?;
+    let mut report = ReloadReport::default();
+
+    macro restart_required($name:literal, $($field:tt)+) {
+        if new.$($field)+ != old.$($field)+ {
+            report.restart_required.push($name.into());
+            new.$($field)+ = old.$($field)+.clone();
+        }
+    }
+
+    restart_required!("listen_port", listen_port);
+    restart_required!("listen_addresses", listen_addresses);
+    restart_required!("max_body_size", max_body_size);
+    restart_required!("backend", backend);
+    // the pool can't be resized or reconfigured while it's in use
+    restart_required!("mysql.pool", mysql.pool);
+    restart_required!("mysql", mysql);
+    restart_required!("sqlite", sqlite);
+    restart_required!("log_upload.max_size", log_upload.max_size);
+    restart_required!("attachment", attachment);
+    restart_required!("import", import);
+
+    match (&old.tls, &new.tls) {
+        (Some(old_tls), Some(tls)) if old_tls != tls => {
+            let tls_config = mutex_lock!(TLS_CONFIG).clone();
+            if let Some(c) = tls_config {
+                c.reload_from_pem_file(&tls.cert, &tls.key).await?;
+                report.applied.push("tls".into());
+            }
+        }
+        (Some(_), Some(_)) | (None, None) => {}
+        _ => restart_required!("tls", tls),
+    }
+
+    if new.shutdown_timeout != old.shutdown_timeout {
+        report.applied.push("shutdown_timeout".into());
+    }
+
+    if new.log_upload != old.log_upload {
+        report.applied.push("log_upload".into());
+    }
+
+    if new.idempotency != old.idempotency {
+        report.applied.push("idempotency".into());
+    }
+
+    if new.admin != old.admin {
+        report.applied.push("admin".into());
+    }
+
+    if new.report != old.report {
+        report.applied.push("report".into());
+    }
+
+    if new.summary != old.summary {
+        for schedule in &new.summary.schedule {
+            summary::parse_schedule(schedule)?;
+        }
+        report.applied.push("summary".into());
+    }
+
+    if new.logging != old.logging {
+        logging::apply_config(new.logging.clone().unwrap_or_default())?;
+        report.applied.push("logging".into());
+    }
+
+    *mutex_lock!(CONFIG) = new;
+    info!("Config reloaded: {:?}", report);
+    Ok(report)
+}
+
+/// Reloads the config on every SIGHUP.
+#[cfg(unix)]
+pub async fn reload_on_sighup() -> std::io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut hangup = signal(SignalKind::hangup())?;
+    while hangup.recv().await.is_some() {
+        info!("Received SIGHUP, reloading config...");
+        if let Err(e) = reload().await {
+            error!("Failed to reload config: {}", e);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,537 @@
+//! Printable PDF reports, to be signed and archived with the paper records
+//!
+//! PDF viewers have no Chinese fonts built in, so a TrueType or OpenType
+//! font with Chinese glyphs is embedded, subset to the characters used.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+
+use anyhow::anyhow;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo, UnicodeCmap};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str};
+use ttf_parser::{name_id, Face, GlyphId};
+
+use crate::config;
+use crate::handlers::InspectionDetails;
+use crate::import::Field;
+use crate::repository::ExportRow;
+
+/// A4, in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+
+const FONT: Name = Name(b"F1");
+
+const SYSTEM_INFO: SystemInfo = SystemInfo {
+    registry: Str(b"Adobe"),
+    ordering: Str(b"Identity"),
+    supplement: 0,
+};
+
+/// The font file of `report.font`
+pub struct Font {
+    data: Vec<u8>,
+    index: u32,
+}
+
+impl Font {
+    pub async fn load(config: &config::Report) -> anyhow::Result<Self> {
+        let Some(path) = &config.font else {
+            return Err(anyhow!("No report font configured"));
+        };
+        let data = tokio::fs::read(path)
+            .await
+            .map_err(|e| anyhow!("Failed to read the report font {}: {}", path, e))?;
+        // fail here rather than on the first report
+        Face::parse(&data, config.font_index)?;
+        Ok(Self {
+            data,
+            index: config.font_index,
+        })
+    }
+}
+
+/// Lays out text and lines on A4 pages, top to bottom.
+struct Writer<'a> {
+    font: &'a Font,
+    face: Face<'a>,
+    /// Glyphs used, and the characters they show
+    glyphs: BTreeMap<u16, char>,
+    pages: Vec<Vec<u8>>,
+    content: Content,
+    /// Where the next row starts, from the bottom
+    y: f32,
+}
+
+impl<'a> Writer<'a> {
+    fn new(font: &'a Font) -> anyhow::Result<Self> {
+        Ok(Self {
+            font,
+            face: Face::parse(&font.data, font.index)?,
+            glyphs: Default::default(),
+            pages: vec![],
+            content: Content::new(),
+            y: PAGE_HEIGHT - MARGIN,
+        })
+    }
+
+    fn glyph(&self, c: char) -> u16 {
+        self.face.glyph_index(c).map(|x| x.0).unwrap_or_default()
+    }
+
+    /// Advance of a glyph, in thousandths of the font size
+    fn advance(&self, glyph: u16) -> f32 {
+        let advance = self.face.glyph_hor_advance(GlyphId(glyph)).unwrap_or_default();
+        advance as f32 * 1000.0 / self.face.units_per_em() as f32
+    }
+
+    fn width(&self, text: &str, size: f32) -> f32 {
+        text.chars()
+            .map(|c| self.advance(self.glyph(c)))
+            .sum::<f32>()
+            * size
+            / 1000.0
+    }
+
+    fn text(&mut self, x: f32, y: f32, size: f32, text: &str) {
+        let mut encoded = Vec::with_capacity(text.len() * 2);
+        for c in text.chars() {
+            let glyph = self.glyph(c);
+            if glyph != 0 {
+                self.glyphs.insert(glyph, c);
+            }
+            encoded.extend(glyph.to_be_bytes());
+        }
+        self.content
+            .begin_text()
+            .set_font(FONT, size)
+            .next_line(x, y)
+            .show(Str(&encoded))
+            .end_text();
+    }
+
+    fn centered(&mut self, y: f32, size: f32, text: &str) {
+        let x = (PAGE_WIDTH - self.width(text, size)) / 2.0;
+        self.text(x, y, size, text);
+    }
+
+    fn line(&mut self, from: (f32, f32), to: (f32, f32)) {
+        self.content
+            .set_line_width(0.5)
+            .move_to(from.0, from.1)
+            .line_to(to.0, to.1)
+            .stroke();
+    }
+
+    /// Splits the text into lines fitting in `width`.
+    fn wrap(&self, text: &str, size: f32, width: f32) -> Vec<String> {
+        let mut lines = vec![];
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            for c in paragraph.chars() {
+                line.push(c);
+                if self.width(&line, size) > width && line.chars().count() > 1 {
+                    line.pop();
+                    lines.push(line);
+                    line = c.to_string();
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Cuts the text to fit in `width`, ending it with an ellipsis if cut.
+    fn truncate(&self, text: &str, size: f32, width: f32) -> String {
+        if self.width(text, size) <= width {
+            return text.into();
+        }
+        let mut cut = text.to_string();
+        while !cut.is_empty() && self.width(&format!("{cut}…"), size) > width {
+            cut.pop();
+        }
+        format!("{cut}…")
+    }
+
+    /// Takes `height` from the page for a row, starting a new page if it
+    /// doesn't fit, and returns the top of the row.
+    fn take(&mut self, height: f32) -> f32 {
+        if self.y - height < MARGIN {
+            self.new_page();
+        }
+        let top = self.y;
+        self.y -= height;
+        top
+    }
+
+    fn new_page(&mut self) {
+        let content = std::mem::replace(&mut self.content, Content::new());
+        self.pages.push(content.finish());
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn finish(mut self) -> anyhow::Result<Vec<u8>> {
+        self.new_page();
+        let mut pdf = Pdf::new();
+        let mut next_ref = Ref::new(1);
+        let mut alloc = || next_ref.bump();
+
+        let catalog_id = alloc();
+        let tree_id = alloc();
+        let font_id = alloc();
+        pdf.catalog(catalog_id).pages(tree_id);
+        let page_ids = self.pages.iter().map(|_| alloc()).collect::<Vec<_>>();
+        pdf.pages(tree_id)
+            .kids(page_ids.iter().copied())
+            .count(page_ids.len() as i32);
+        for (content, &page_id) in self.pages.iter().zip(&page_ids) {
+            let content_id = alloc();
+            let mut page = pdf.page(page_id);
+            page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+                .parent(tree_id)
+                .contents(content_id);
+            page.resources().fonts().pair(FONT, font_id);
+            page.finish();
+            pdf.stream(content_id, &deflate(content)?)
+                .filter(Filter::FlateDecode);
+        }
+
+        self.write_font(&mut pdf, font_id, &mut alloc)?;
+        Ok(pdf.finish())
+    }
+
+    /// Writes the font as a Type0 font of glyph ids, i.e. `Identity-H`.
+    fn write_font(
+        &self,
+        pdf: &mut Pdf,
+        font_id: Ref,
+        alloc: &mut impl FnMut() -> Ref,
+    ) -> anyhow::Result<()> {
+        let (cid_id, descriptor_id, cmap_id, file_id) = (alloc(), alloc(), alloc(), alloc());
+        let face = &self.face;
+        let cff = face.tables().cff.is_some();
+
+        let mut glyphs = vec![0];
+        glyphs.extend(self.glyphs.keys());
+        let subset = subsetter::subset(
+            &self.font.data,
+            self.font.index,
+            subsetter::Profile::pdf(&glyphs),
+        )
+        .map_err(|e| anyhow!("Failed to subset the report font: {:?}", e))?;
+
+        let postscript_name = face
+            .names()
+            .into_iter()
+            .find(|x| x.name_id == name_id::POST_SCRIPT_NAME)
+            .and_then(|x| x.to_string())
+            .unwrap_or_else(|| "Font".into());
+        let base_font = format!("{}+{}", subset_tag(&glyphs), postscript_name);
+        let base_font = Name(base_font.as_bytes());
+
+        pdf.type0_font(font_id)
+            .base_font(base_font)
+            .encoding_predefined(Name(b"Identity-H"))
+            .descendant_font(cid_id)
+            .to_unicode(cmap_id);
+
+        let mut cid = pdf.cid_font(cid_id);
+        cid.subtype(if cff {
+            CidFontType::Type0
+        } else {
+            CidFontType::Type2
+        })
+        .base_font(base_font)
+        .system_info(SYSTEM_INFO)
+        .font_descriptor(descriptor_id)
+        .default_width(0.0);
+        if !cff {
+            cid.cid_to_gid_map_predefined(Name(b"Identity"));
+        }
+        let mut widths = cid.widths();
+        for &glyph in &glyphs {
+            widths.consecutive(glyph, [self.advance(glyph)]);
+        }
+        widths.finish();
+        cid.finish();
+
+        let scale = |x: i16| x as f32 * 1000.0 / face.units_per_em() as f32;
+        let bbox = face.global_bounding_box();
+        let mut descriptor = pdf.font_descriptor(descriptor_id);
+        descriptor
+            .name(base_font)
+            .flags(FontFlags::SYMBOLIC)
+            .bbox(Rect::new(
+                scale(bbox.x_min),
+                scale(bbox.y_min),
+                scale(bbox.x_max),
+                scale(bbox.y_max),
+            ))
+            .italic_angle(0.0)
+            .ascent(scale(face.ascender()))
+            .descent(scale(face.descender()))
+            .cap_height(scale(face.capital_height().unwrap_or(face.ascender())))
+            .stem_v(80.0);
+        if cff {
+            descriptor.font_file3(file_id);
+        } else {
+            descriptor.font_file2(file_id);
+        }
+        descriptor.finish();
+
+        let mut cmap = UnicodeCmap::new(Name(b"Custom"), SYSTEM_INFO);
+        for (&glyph, &c) in &self.glyphs {
+            cmap.pair(glyph, c);
+        }
+        pdf.stream(cmap_id, &cmap.finish());
+
+        let compressed = deflate(&subset)?;
+        let mut file = pdf.stream(file_id, &compressed);
+        file.filter(Filter::FlateDecode);
+        if cff {
+            file.pair(Name(b"Subtype"), Name(b"OpenType"));
+        }
+        file.finish();
+        Ok(())
+    }
+}
+
+fn deflate(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// The six-letter prefix of subset font names
+fn subset_tag(glyphs: &[u16]) -> String {
+    let mut hasher = DefaultHasher::new();
+    glyphs.hash(&mut hasher);
+    let mut hash = Hasher::finish(&hasher);
+    (0..6)
+        .map(|_| {
+            let c = (b'A' + (hash % 26) as u8) as char;
+            hash /= 26;
+            c
+        })
+        .collect()
+}
+
+fn inspection_state(flag: i32) -> &'static str {
+    match flag {
+        1 => "终检",
+        _ => "初检",
+    }
+}
+
+fn break_location(break_flag: bool) -> &'static str {
+    match break_flag {
+        true => "拉丝池",
+        false => "非拉丝池",
+    }
+}
+
+fn signatures(writer: &mut Writer, labels: &[&str]) {
+    let top = writer.take(60.0);
+    let width = (PAGE_WIDTH - 2.0 * MARGIN) / labels.len() as f32;
+    for (i, label) in labels.iter().enumerate() {
+        let x = MARGIN + width * i as f32;
+        writer.text(x, top - 40.0, 11.0, &format!("{label}："));
+        let start = x + writer.width(label, 11.0) + 14.0;
+        writer.line((start, top - 42.0), (x + width - 12.0, top - 42.0));
+    }
+}
+
+fn printed_at(writer: &mut Writer) {
+    let top = writer.take(24.0);
+    let now = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+    writer.text(MARGIN, top - 16.0, 8.0, &format!("打印时间：{now}"));
+}
+
+/// The record of one inspection, with the fields as labeled in the app
+pub fn inspection(details: &InspectionDetails, font: &Font) -> anyhow::Result<Vec<u8>> {
+    let mut writer = Writer::new(font)?;
+    let top = writer.take(40.0);
+    writer.centered(top - 20.0, 18.0, "断线检验记录");
+    let top = writer.take(20.0);
+    let heading = format!("编号：{}", details.id);
+    writer.text(MARGIN, top - 12.0, 10.0, &heading);
+    let state = format!("状态：{}", inspection_state(details.inspection_flag));
+    let x = PAGE_WIDTH - MARGIN - writer.width(&state, 10.0);
+    writer.text(x, top - 12.0, 10.0, &state);
+
+    let cause = |x: &Option<crate::handlers::BreakCause>| {
+        x.as_ref().and_then(|x| x.cause.clone()).unwrap_or_default()
+    };
+    let text = |x: &Option<String>| x.clone().unwrap_or_default();
+    let mut rows = vec![
+        (Field::DeviceCode.label(), details.device_code.to_string()),
+        (Field::DeviceCategory.label(), details.device_category.clone()),
+        (Field::Creator.label(), details.creator.name.clone()),
+        (Field::CreationTime.label(), details.creation_time.clone()),
+        (Field::ProductSpec.label(), text(&details.product_spec)),
+        (
+            Field::WireNumber.label(),
+            details.wire_num.map(|x| x.to_string()).unwrap_or_default(),
+        ),
+        (Field::WireType.label(), text(&details.wire_type)),
+        (Field::BreakSpec.label(), details.break_spec.clone()),
+        (Field::WireBatchCode.label(), text(&details.wire_batch_code)),
+        (Field::StickBatchCode.label(), text(&details.stick_batch_code)),
+        (Field::Warehouse.label(), text(&details.warehouse)),
+        (Field::ProductTime.label(), text(&details.product_time)),
+        ("断线位置", break_location(details.break_flag).into()),
+    ];
+    match details.break_flag {
+        true => rows.push((
+            Field::BreakpointB.label(),
+            details.breakpoint_b.as_ref().map(|x| x.to_string()).unwrap_or_default(),
+        )),
+        false => rows.push((
+            Field::BreakpointA.label(),
+            details
+                .breakpoint_a
+                .as_ref()
+                .and_then(|x| x.breakpoint.clone())
+                .unwrap_or_default(),
+        )),
+    }
+    rows.extend([
+        (Field::BreakCauseA.label(), cause(&details.break_cause_a)),
+        ("终检原因", cause(&details.break_cause_b)),
+        (
+            "检验员",
+            details.inspector.as_ref().map(|x| x.name.clone()).unwrap_or_default(),
+        ),
+        ("检验时间", text(&details.inspection_time)),
+        (Field::Comments.label(), text(&details.comments)),
+        ("附件", format!("{} 张", details.attachments.len())),
+    ]);
+
+    const SIZE: f32 = 11.0;
+    const LABEL_WIDTH: f32 = 120.0;
+    let value_x = MARGIN + LABEL_WIDTH + 8.0;
+    let value_width = PAGE_WIDTH - MARGIN - value_x - 8.0;
+    let top = writer.take(8.0);
+    writer.line((MARGIN, top), (PAGE_WIDTH - MARGIN, top));
+    for (label, value) in rows {
+        let lines = writer.wrap(&value, SIZE, value_width);
+        let height = 8.0 + 16.0 * lines.len() as f32;
+        let top = writer.take(height);
+        writer.text(MARGIN + 8.0, top - 17.0, SIZE, label);
+        for (i, line) in lines.iter().enumerate() {
+            writer.text(value_x, top - 17.0 - 16.0 * i as f32, SIZE, line);
+        }
+        let bottom = top - height;
+        writer.line((MARGIN, bottom), (PAGE_WIDTH - MARGIN, bottom));
+        writer.line((MARGIN + LABEL_WIDTH, top), (MARGIN + LABEL_WIDTH, bottom));
+    }
+
+    signatures(&mut writer, &["创建人签字", "检验员签字", "日期"]);
+    printed_at(&mut writer);
+    writer.finish()
+}
+
+/// Counts of the inspections of a stage created from the day `from` to the
+/// day `to`, and a list of them
+pub fn summary(
+    stage: u32,
+    from: &str,
+    to: &str,
+    rows: &[ExportRow],
+    font: &Font,
+) -> anyhow::Result<Vec<u8>> {
+    let mut writer = Writer::new(font)?;
+    let top = writer.take(40.0);
+    writer.centered(top - 20.0, 18.0, "断线汇总");
+    let top = writer.take(20.0);
+    let heading = format!("工序：{stage}    期间：{from} 至 {to}");
+    writer.text(MARGIN, top - 12.0, 10.0, &heading);
+
+    let finalized = rows.iter().filter(|x| x.inspection_flag == 1).count();
+    let in_pool = rows.iter().filter(|x| x.break_flag).count();
+    let lines = [
+        format!(
+            "合计 {} 条；初检 {} 条，终检 {} 条",
+            rows.len(),
+            rows.len() - finalized,
+            finalized
+        ),
+        format!("拉丝池 {} 条，非拉丝池 {} 条", in_pool, rows.len() - in_pool),
+    ];
+    for line in lines {
+        let top = writer.take(18.0);
+        writer.text(MARGIN, top - 14.0, 11.0, &line);
+    }
+
+    let mut causes = HashMap::<&str, usize>::new();
+    for row in rows {
+        *causes.entry(row.cause().unwrap_or("未填写")).or_default() += 1;
+    }
+    let mut causes = causes.into_iter().collect::<Vec<_>>();
+    causes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    if !causes.is_empty() {
+        let top = writer.take(26.0);
+        writer.text(MARGIN, top - 20.0, 12.0, "断线原因");
+        for (cause, count) in causes {
+            let top = writer.take(16.0);
+            writer.text(MARGIN + 12.0, top - 12.0, 10.0, cause);
+            writer.text(MARGIN + 200.0, top - 12.0, 10.0, &format!("{count} 条"));
+        }
+    }
+
+    const SIZE: f32 = 8.0;
+    const COLUMNS: [(&str, f32); 8] = [
+        ("编号", 72.0),
+        ("创建时间", 82.0),
+        ("机台号", 40.0),
+        ("断线规格", 48.0),
+        ("断线位置", 44.0),
+        ("断线原因", 102.0),
+        ("状态", 32.0),
+        ("创建人", 75.0),
+    ];
+    let header = |writer: &mut Writer| {
+        let top = writer.take(18.0);
+        let mut x = MARGIN;
+        for (title, width) in COLUMNS {
+            writer.text(x + 2.0, top - 13.0, SIZE, title);
+            x += width;
+        }
+        writer.line((MARGIN, top - 18.0), (PAGE_WIDTH - MARGIN, top - 18.0));
+    };
+    let top = writer.take(12.0);
+    writer.line((MARGIN, top - 12.0), (PAGE_WIDTH - MARGIN, top - 12.0));
+    header(&mut writer);
+    for row in rows {
+        if writer.y - 14.0 < MARGIN {
+            writer.new_page();
+            header(&mut writer);
+        }
+        let cells = [
+            row.id.to_string(),
+            row.creation_time.clone(),
+            row.device_code.to_string(),
+            row.break_spec.clone(),
+            break_location(row.break_flag).into(),
+            row.cause().unwrap_or_default().into(),
+            inspection_state(row.inspection_flag).into(),
+            row.creator.clone().unwrap_or_default(),
+        ];
+        let top = writer.take(14.0);
+        let mut x = MARGIN;
+        for (cell, (_, width)) in cells.iter().zip(COLUMNS) {
+            let cell = writer.truncate(cell, SIZE, width - 4.0);
+            writer.text(x + 2.0, top - 10.0, SIZE, &cell);
+            x += width;
+        }
+    }
+
+    signatures(&mut writer, &["制表", "审核", "日期"]);
+    printed_at(&mut writer);
+    writer.finish()
+}
@@ -0,0 +1,1304 @@
+//! Storage behind the handlers
+//!
+//! Handlers only see these traits through the [`crate::ApiContext`], so
+//! they can run against [`mysql::MySqlRepository`] in production,
+//! [`sqlite::SqliteRepository`] on machines without MySQL, and an
+//! in-memory fake in tests.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::{Local, NaiveDateTime, TimeZone};
+use czttgd_api::{
+    BatchOperation, BatchResult, BatchStatus, Changes, HistoryQuery, MachineHistory, SearchQuery,
+    SyncItem, SyncResult, SyncStatus,
+};
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::handlers::{
+    Attachment, BreakCause, Breakpoint, InspectionDetails, InspectionForm, InspectionSummary, User,
+};
+use crate::RefKind;
+
+pub mod mysql;
+pub mod sqlite;
+
+#[async_trait]
+pub trait InspectionRepository: Send + Sync {
+    /// Inserts a new inspection, and returns the id allocated for it.
+    async fn create(&self, form: InspectionForm) -> anyhow::Result<i64>;
+
+    /// Like [`Self::create`], unless `key` has created an inspection within
+    /// `window`. Returns the id, and whether it's just created.
+    async fn create_once(
+        &self,
+        key: &str,
+        window: Duration,
+        form: InspectionForm,
+    ) -> anyhow::Result<(i64, bool)>;
+
+    /// Replaces the fields of an inspection, turning it back into 初检.
+    /// Returns `false` if there's no such inspection.
+    async fn update(&self, id: i64, form: InspectionForm) -> anyhow::Result<bool>;
+
+    /// Inspections not deleted, the newest first
+    async fn search(&self, query: &SearchQuery) -> anyhow::Result<Vec<InspectionSummary>>;
+
+    /// The inspection with its references and attachments resolved
+    async fn details(&self, id: i64) -> anyhow::Result<Option<InspectionDetails>>;
+
+    /// Number of all the inspections
+    async fn count(&self) -> anyhow::Result<i64>;
+
+    /// Whether the inspection exists, deleted or not
+    async fn exists(&self, id: i64) -> anyhow::Result<bool>;
+
+    /// Soft-deletes or restores an inspection. Returns `false` if there's no
+    /// such inspection, or it's already in that state.
+    async fn set_deleted(&self, id: i64, deleted: bool) -> anyhow::Result<bool>;
+
+    /// The serial used in the next inspection id
+    async fn serial(&self) -> anyhow::Result<i32>;
+
+    async fn set_serial(&self, serial: i32) -> anyhow::Result<()>;
+
+    /// Inspections not deleted, created from the day `from` to the day `to`
+    /// inclusive, the oldest first
+    async fn export(&self, from: &str, to: &str) -> anyhow::Result<Vec<ExportRow>>;
+
+    /// Applies the operation to the inspections in one transaction, and
+    /// returns the outcome of each id, in order, with the machine of the
+    /// inspection if it exists.
+    async fn batch(
+        &self,
+        ids: &[i64],
+        operation: &BatchOperation,
+    ) -> anyhow::Result<Vec<(BatchResult, Option<i32>)>>;
+
+    /// Inserts the inspections in one transaction, with ids made of their
+    /// `creation_time`s. Returns the id or the error of each, in order; the
+    /// transaction is committed only if all succeed and it's not `dry_run`.
+    async fn import(
+        &self,
+        forms: Vec<InspectionForm>,
+        dry_run: bool,
+    ) -> anyhow::Result<Vec<Result<i64, String>>>;
+
+    /// Breaks of a machine, or `None` if there's no such machine
+    async fn history(
+        &self,
+        device_code: i32,
+        query: &HistoryQuery,
+    ) -> anyhow::Result<Option<MachineHistory>>;
+}
+
+/// Users, break causes, breakpoints and machines
+#[async_trait]
+pub trait MasterDataRepository: Send + Sync {
+    async fn users(&self) -> anyhow::Result<Vec<User>>;
+
+    async fn break_causes(&self) -> anyhow::Result<Vec<BreakCause>>;
+
+    async fn breakpoints(&self) -> anyhow::Result<Vec<Breakpoint>>;
+
+    /// Machine numbers of a stage
+    async fn devices(&self, stage: i32) -> anyhow::Result<Vec<i32>>;
+
+    /// Stage of a machine, or `None` if there's no such machine
+    async fn machine_stage(&self, device_code: i32) -> anyhow::Result<Option<i32>>;
+
+    /// Sets `enablestate`. Returns `false` if there's no such record, or
+    /// it's unchanged.
+    async fn set_state(&self, kind: RefKind, id: i32, state: i32) -> anyhow::Result<bool>;
+}
+
+/// Metadata of the photos attached to inspections
+#[async_trait]
+pub trait AttachmentRepository: Send + Sync {
+    /// Inserts the metadata of an attachment already put into the store.
+    async fn insert(&self, attachment: NewAttachment) -> anyhow::Result<Attachment>;
+
+    async fn list(&self, inspection_id: i64) -> anyhow::Result<Vec<Attachment>>;
+
+    async fn get(&self, id: i64) -> anyhow::Result<Option<Attachment>>;
+
+    async fn delete(&self, id: i64) -> anyhow::Result<()>;
+}
+
+/// The offline sync of the tablets
+///
+/// Changes of inspections made through the other repositories take versions
+/// too, but not the ones made to `tt_inspect` directly.
+#[async_trait]
+pub trait SyncRepository: Send + Sync {
+    /// Applies the items in order, in one transaction. `client_id`s must be
+    /// hyphenated lowercase UUIDs.
+    async fn push(&self, items: Vec<SyncItem>) -> anyhow::Result<Vec<SyncResult>>;
+
+    /// Up to `limit` inspections changed after the version `since`
+    async fn changes(&self, since: i64, limit: u64) -> anyhow::Result<Changes>;
+}
+
+/// Copying all the data between backends
+#[async_trait]
+pub trait Transfer: Send + Sync {
+    async fn dump(&self) -> anyhow::Result<Dump>;
+
+    /// Inserts everything in one transaction, keeping the ids. Fails if
+    /// there are inspections or master data already.
+    async fn load(&self, dump: &Dump) -> anyhow::Result<()>;
+}
+
+/// The repositories of one backend
+pub struct Repositories {
+    pub inspections: Arc<dyn InspectionRepository>,
+    pub master_data: Arc<dyn MasterDataRepository>,
+    pub attachments: Arc<dyn AttachmentRepository>,
+    pub sync: Arc<dyn SyncRepository>,
+    pub transfer: Arc<dyn Transfer>,
+}
+
+impl Repositories {
+    pub fn new<R>(repository: Arc<R>) -> Self
+    where
+        R: InspectionRepository
+            + MasterDataRepository
+            + AttachmentRepository
+            + SyncRepository
+            + Transfer
+            + 'static,
+    {
+        Self {
+            inspections: repository.clone(),
+            master_data: repository.clone(),
+            attachments: repository.clone(),
+            sync: repository.clone(),
+            transfer: repository,
+        }
+    }
+}
+
+pub struct NewAttachment {
+    pub inspection_id: i64,
+    pub filename: Option<String>,
+    pub content_type: String,
+    pub size: i64,
+    pub width: u32,
+    pub height: u32,
+    pub object_key: String,
+    pub thumbnail_key: String,
+}
+
+/// An inspection with its references replaced by names, for spreadsheets
+#[derive(FromRow, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRow {
+    pub id: i64,
+    pub device_code: i32,
+    pub device_category: String,
+    pub creation_time: String,
+    pub creator: Option<String>,
+    /// 0: 已初检 1: 已终检
+    pub inspection_flag: i32,
+    pub product_spec: Option<String>,
+    pub wire_num: Option<i32>,
+    pub wire_type: Option<String>,
+    pub break_spec: String,
+    pub wire_batch_code: Option<String>,
+    pub stick_batch_code: Option<String>,
+    pub warehouse: Option<String>,
+    pub product_time: Option<String>,
+    pub break_flag: bool,
+    /// Two decimal places, like `12.50`
+    pub breakpoint_b: Option<String>,
+    pub breakpoint_a: Option<String>,
+    pub break_cause_a: Option<String>,
+    pub break_cause_b: Option<String>,
+    pub comments: Option<String>,
+    pub inspector: Option<String>,
+    pub inspection_time: Option<String>,
+}
+
+impl ExportRow {
+    /// The final cause once finalized, otherwise the initial one
+    pub fn cause(&self) -> Option<&str> {
+        self.break_cause_b.as_deref().or(self.break_cause_a.as_deref())
+    }
+}
+
+/// Everything in the schema
+pub struct Dump {
+    pub users: Vec<User>,
+    pub break_causes: Vec<BreakCause>,
+    pub breakpoints: Vec<Breakpoint>,
+    /// `(machinenumber, stage)`
+    pub machines: Vec<(i32, i32)>,
+    pub serial: i32,
+    pub inspections: Vec<InspectionRow>,
+    pub attachments: Vec<Attachment>,
+    pub sync_version: i64,
+    /// `(inspection_id, version)`
+    pub versions: Vec<(i64, i64)>,
+    pub synced: Vec<SyncedForm>,
+}
+
+/// A `tt_inspect` row as is
+#[derive(FromRow, Debug)]
+pub struct InspectionRow {
+    pub id: i64,
+    pub creator: i32,
+    pub devicecode: i32,
+    pub devicecategory: String,
+    pub creationtime: String,
+    pub spec: Option<String>,
+    pub wirenum: Option<i32>,
+    pub wiretype: Option<String>,
+    pub breakspec: String,
+    pub twbatchcode: Option<String>,
+    pub trbatchcode: Option<String>,
+    pub dlwarehouse: Option<String>,
+    pub tgproducttime: Option<String>,
+    pub breakflag: String,
+    pub breakpointa: Option<i32>,
+    pub breakpointb: Option<String>,
+    pub memo: Option<String>,
+    pub breakreasona: Option<i32>,
+    pub breakreasonb: Option<i32>,
+    pub billflag: i32,
+    pub inspector: Option<String>,
+    pub inspecttime: Option<String>,
+    pub deleteflag: i32,
+}
+
+/// What a client pushed last under a `client_id`
+#[derive(FromRow, Debug)]
+pub struct SyncedForm {
+    pub client_id: String,
+    pub inspection_id: i64,
+    /// [`InspectionForm`] as JSON
+    pub form: String,
+    pub version: i64,
+}
+
+/// How to apply a pushed item
+enum SyncAction {
+    Create,
+    Update(i64),
+    /// Leaves the inspection as is.
+    Keep(i64, SyncStatus),
+}
+
+/// Decides from the item, what its client pushed last, and the current
+/// version of that inspection.
+///
+/// A client may edit what it pushed freely as long as no one else changes it.
+/// After that, its edits need a `base_version` not older than the current
+/// version, or the server's copy wins.
+fn resolve(item: &SyncItem, form: &str, synced: Option<(&SyncedForm, i64)>) -> SyncAction {
+    let Some((synced, current)) = synced else {
+        return SyncAction::Create;
+    };
+    let id = synced.inspection_id;
+    if synced.form == form {
+        SyncAction::Keep(id, SyncStatus::Unchanged)
+    } else if synced.version == current || item.base_version.is_some_and(|x| x >= current) {
+        SyncAction::Update(id)
+    } else {
+        SyncAction::Keep(id, SyncStatus::Conflict)
+    }
+}
+
+/// The columns of an inspection that batch operations depend on
+#[derive(FromRow)]
+struct BatchTarget {
+    devicecode: i32,
+    billflag: i32,
+    deleteflag: i32,
+    breakreasona: Option<i32>,
+    breakreasonb: Option<i32>,
+}
+
+/// What a batch operation does to one inspection
+enum BatchChange<'a> {
+    Finalize { cause: i32, inspector: &'a str },
+    SetDeleted(bool),
+    CauseA(i32),
+    CauseB(i32),
+}
+
+/// Decides what `operation` does to an inspection, or why it does nothing.
+fn plan<'a>(
+    operation: &'a BatchOperation,
+    target: Option<&BatchTarget>,
+) -> Result<BatchChange<'a>, BatchStatus> {
+    let Some(target) = target else {
+        return Err(BatchStatus::NotFound);
+    };
+    let deleted = target.deleteflag != 0;
+    let finalized = target.billflag == 1;
+    match *operation {
+        BatchOperation::Restore if deleted => Ok(BatchChange::SetDeleted(false)),
+        BatchOperation::Restore => Err(BatchStatus::Unchanged),
+        _ if deleted => Err(BatchStatus::Deleted),
+        BatchOperation::Delete => Ok(BatchChange::SetDeleted(true)),
+        BatchOperation::Finalize { cause, .. } | BatchOperation::ReassignCause { cause }
+            if finalized && target.breakreasonb == Some(cause) =>
+        {
+            Err(BatchStatus::Unchanged)
+        }
+        BatchOperation::Finalize {
+            cause,
+            ref inspector,
+        } => Ok(BatchChange::Finalize { cause, inspector }),
+        BatchOperation::ReassignCause { cause } if finalized => Ok(BatchChange::CauseB(cause)),
+        BatchOperation::ReassignCause { cause } if target.breakreasona == Some(cause) => {
+            Err(BatchStatus::Unchanged)
+        }
+        BatchOperation::ReassignCause { cause } => Ok(BatchChange::CauseA(cause)),
+    }
+}
+
+/// Mean time between `break_count` breaks spanning `span_secs`
+fn mtbf_secs(break_count: i64, span_secs: Option<i64>) -> Option<f64> {
+    match span_secs {
+        Some(s) if break_count >= 2 => Some(s as f64 / (break_count - 1) as f64),
+        _ => None,
+    }
+}
+
+/// Binds `devicecode = ?` and the `creationtime` window.
+macro bind_window($query:expr, $code:expr, $window:expr) {
+    $query
+        .bind($code)
+        .bind(&$window.from)
+        .bind(&$window.from)
+        .bind(&$window.to)
+        .bind(&$window.to)
+}
+
+/// Next serial after `serial`; it wraps from 999 to 1.
+fn next_serial(serial: i32) -> i32 {
+    match serial + 1 {
+        // reset to 001
+        1000 => 1,
+        x => x,
+    }
+}
+
+/// timestamp+<counter>
+///
+/// `counter` has three-width padding, like 001, 002...
+fn inspection_id(timestamp: u64, serial: i32) -> anyhow::Result<i64> {
+    Ok(format!("{timestamp}{:03}", serial).parse::<i64>()?)
+}
+
+/// `creationtime` and `inspecttime` as the tablets send them
+pub const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// The local time in [`TIME_FORMAT`]
+fn local_time_now() -> String {
+    Local::now().format(TIME_FORMAT).to_string()
+}
+
+/// Unix timestamp of a `creationtime`, in the local time zone
+fn creation_timestamp(creation_time: &str) -> anyhow::Result<u64> {
+    let time = NaiveDateTime::parse_from_str(creation_time, TIME_FORMAT)?;
+    let Some(time) = Local.from_local_datetime(&time).earliest() else {
+        return Err(anyhow!("No such local time: {}", creation_time));
+    };
+    Ok(time.timestamp().try_into()?)
+}
+
+/// Id of an imported inspection: the next one after `max`, the greatest
+/// id taken in the second of its `creationtime`, so imports don't touch
+/// the serial of `tt_number`.
+fn imported_id(timestamp: u64, max: Option<i64>) -> anyhow::Result<i64> {
+    match max {
+        None => inspection_id(timestamp, 1),
+        Some(id) if id % 1000 < 999 => Ok(id + 1),
+        Some(_) => Err(anyhow!("No free id in the second {}", timestamp)),
+    }
+}
+
+/// The `(first, last)` ids of a second
+fn id_range(timestamp: u64) -> anyhow::Result<(i64, i64)> {
+    Ok((inspection_id(timestamp, 0)?, inspection_id(timestamp, 999)?))
+}
+
+/// `breakpointb` as MySQL's `DECIMAL(10, 2)` would store it
+fn decimal_text(value: Option<String>) -> anyhow::Result<Option<String>> {
+    let decimal = value.map(|x| x.parse::<BigDecimal>()).transpose()?;
+    Ok(decimal.map(|x| x.round(2).with_scale(2).to_string()))
+}
+
+/// Implements all the repositories for `$repository`, a struct with a
+/// `db: sqlx::Pool<$database>`.
+///
+/// The backends share the row mapping and the transactions; they differ
+/// only in the SQL, which `$sql!("name")` picks, and in `$last_insert_id`
+/// reading the id of an inserted row from a query result. Rows carrying
+/// `breakpointb` are mapped by hand, as SQLite can't decode
+/// [`BigDecimal`]; the queries return it as text.
+macro_rules! sql_repository {
+    ($repository:ident, $database:ty, $sql:ident, $last_insert_id:path) => {
+        use std::collections::HashMap;
+        use std::time::Duration;
+
+        use async_trait::async_trait;
+        use bigdecimal::BigDecimal;
+        use czttgd_api::{
+            BatchOperation, BatchResult, BatchStatus, BreakpointFrequency, CauseFrequency, Changes,
+            HistoryQuery, InspectionChange, MachineBreak, MachineHistory, SearchQuery, SyncItem,
+            SyncResult, SyncStatus,
+        };
+        use futures::TryStreamExt;
+        use sqlx::database::HasArguments;
+        use sqlx::query::Query;
+        use sqlx::{Database, FromRow, Row};
+
+        use $crate::handlers::{
+            Attachment, BreakCause, Breakpoint, InspectionDetails, InspectionForm,
+            InspectionSummary, User,
+        };
+        use $crate::repository::{
+            bind_window, creation_timestamp, decimal_text, id_range, imported_id, inspection_id,
+            local_time_now, mtbf_secs, next_serial, plan, resolve, AttachmentRepository,
+            BatchChange, BatchTarget, Dump, ExportRow, InspectionRepository, MasterDataRepository,
+            NewAttachment, SyncAction, SyncRepository, SyncedForm, Transfer,
+        };
+        use $crate::{check_from_row, RefKind};
+
+        type Connection = <$database as Database>::Connection;
+        type DbRow = <$database as Database>::Row;
+        type Arguments<'q> = <$database as HasArguments<'q>>::Arguments;
+
+        /// Takes the next sync version. Do this first in the transactions
+        /// changing inspections: MySQL keeps the `sync_version` row locked
+        /// until the transaction ends, and on SQLite starting with a write
+        /// takes the database lock, where a deferred transaction reading first
+        /// would fail with `SQLITE_BUSY` instead of waiting when it writes.
+        async fn next_version(conn: &mut Connection) -> anyhow::Result<i64> {
+            sqlx::query($sql!("sync-version-next"))
+                .execute(&mut *conn)
+                .await?;
+            let row = sqlx::query($sql!("sync-version"))
+                .fetch_one(&mut *conn)
+                .await?;
+            Ok(row.try_get("version")?)
+        }
+
+        async fn set_version(conn: &mut Connection, id: i64, version: i64) -> anyhow::Result<()> {
+            sqlx::query($sql!("inspection-version-set"))
+                .bind(id)
+                .bind(version)
+                .execute(conn)
+                .await?;
+            Ok(())
+        }
+
+        /// Inserts an inspection with the next serial, and returns its id.
+        async fn insert(conn: &mut Connection, form: InspectionForm) -> anyhow::Result<i64> {
+            let serial: i32 = sqlx::query($sql!("inspection-counter"))
+                .fetch_one(&mut *conn)
+                .await?
+                .try_get("num")?;
+            sqlx::query($sql!("counter-update"))
+                .bind(next_serial(serial))
+                .execute(&mut *conn)
+                .await?;
+            let id = inspection_id($crate::timestamp_secs(), serial)?;
+            let query = bind_form(sqlx::query($sql!("inspection-post")), form)?;
+            // bind: id
+            query.bind(id).execute(conn).await?;
+            Ok(id)
+        }
+
+        /// Inserts an imported inspection under an id of its `creation_time`.
+        async fn insert_imported(
+            conn: &mut Connection,
+            form: InspectionForm,
+        ) -> anyhow::Result<i64> {
+            let timestamp = creation_timestamp(&form.creation_time)?;
+            let (first, last) = id_range(timestamp)?;
+            let max: Option<i64> = sqlx::query($sql!("inspection-id-max"))
+                .bind(first)
+                .bind(last)
+                .fetch_one(&mut *conn)
+                .await?
+                .try_get("id")?;
+            let id = imported_id(timestamp, max)?;
+            let query = bind_form(sqlx::query($sql!("inspection-post")), form)?;
+            // bind: id
+            query.bind(id).execute(conn).await?;
+            Ok(id)
+        }
+
+        async fn apply(
+            conn: &mut Connection,
+            id: i64,
+            change: BatchChange<'_>,
+            now: &str,
+        ) -> anyhow::Result<()> {
+            let query = match change {
+                BatchChange::Finalize { cause, inspector } => {
+                    sqlx::query($sql!("inspection-finalize"))
+                        .bind(cause)
+                        .bind(inspector)
+                        .bind(now)
+                }
+                BatchChange::SetDeleted(deleted) => {
+                    sqlx::query($sql!("inspection-set-deleted")).bind(deleted as i32)
+                }
+                BatchChange::CauseA(cause) => {
+                    sqlx::query($sql!("inspection-set-cause-a")).bind(cause)
+                }
+                BatchChange::CauseB(cause) => {
+                    sqlx::query($sql!("inspection-set-cause-b")).bind(cause)
+                }
+            };
+            let mut query = query.bind(id);
+            if let BatchChange::SetDeleted(deleted) = change {
+                // AND deleteflag <> ?
+                query = query.bind(deleted as i32);
+            }
+            query.execute(conn).await?;
+            Ok(())
+        }
+
+        /// Returns whether the inspection exists.
+        async fn update(
+            conn: &mut Connection,
+            id: i64,
+            form: InspectionForm,
+        ) -> anyhow::Result<bool> {
+            let query = bind_form(sqlx::query($sql!("inspection-update")), form)?;
+            // WHERE id = ?
+            let result = query.bind(id).execute(&mut *conn).await?;
+            if result.rows_affected() > 0 {
+                return Ok(true);
+            }
+            // unchanged, on MySQL
+            let exists = sqlx::query($sql!("inspection-exists"))
+                .bind(id)
+                .fetch_one(conn)
+                .await?
+                .try_get::<i64, _>("c")?
+                > 0;
+            Ok(exists)
+        }
+
+        fn bind_form<'q>(
+            query: Query<'q, $database, Arguments<'q>>,
+            form: InspectionForm,
+        ) -> anyhow::Result<Query<'q, $database, Arguments<'q>>> {
+            // They use `char(1)`. According the conversion,
+            // we must insert a `&str`.
+            let break_flag = match form.break_flag {
+                true => "1",
+                false => "0",
+            };
+
+            Ok(query
+                .bind(form.creator)
+                .bind(form.device_code)
+                .bind(form.creation_time)
+                .bind(form.product_spec)
+                .bind(form.wire_number)
+                .bind(form.wire_type)
+                .bind(form.break_spec)
+                .bind(form.wire_batch_code)
+                .bind(form.stick_batch_code)
+                .bind(form.warehouse)
+                .bind(form.product_time)
+                .bind(break_flag)
+                .bind(form.breakpoint_a)
+                .bind(decimal_text(form.breakpoint_b)?)
+                .bind(form.comments)
+                .bind(form.device_category)
+                .bind(form.break_cause_a))
+        }
+
+        fn get_decimal(row: &DbRow, column: &str) -> anyhow::Result<Option<BigDecimal>> {
+            let text: Option<String> = row.try_get(column)?;
+            Ok(text.map(|x| x.parse()).transpose()?)
+        }
+
+        fn breakpoint_a(r: &DbRow) -> sqlx::Result<Option<Breakpoint>> {
+            check_from_row(r, "breakpoint_a", |r| {
+                Breakpoint::from_row_prefixed(r, "bp_a")
+            })
+        }
+
+        fn break_cause(r: &DbRow, column: &str, prefix: &str) -> sqlx::Result<Option<BreakCause>> {
+            check_from_row(r, column, |r| BreakCause::from_row_prefixed(r, prefix))
+        }
+
+        fn details_from_row(r: &DbRow) -> anyhow::Result<InspectionDetails> {
+            Ok(InspectionDetails {
+                id: r.try_get("id")?,
+                device_code: r.try_get("device_code")?,
+                device_category: r.try_get("device_category")?,
+                creator: User::from_row(r)?,
+                creation_time: r.try_get("creation_time")?,
+                inspection_flag: r.try_get("inspection_flag")?,
+                product_spec: r.try_get("product_spec")?,
+                wire_num: r.try_get("wire_num")?,
+                wire_type: r.try_get("wire_type")?,
+                break_spec: r.try_get("break_spec")?,
+                wire_batch_code: r.try_get("wire_batch_code")?,
+                stick_batch_code: r.try_get("stick_batch_code")?,
+                warehouse: r.try_get("warehouse")?,
+                product_time: r.try_get("product_time")?,
+                break_flag: r.try_get("break_flag")?,
+                breakpoint_b: get_decimal(r, "breakpoint_b")?,
+                breakpoint_a: breakpoint_a(r)?,
+                break_cause_a: break_cause(r, "break_cause_a", "br_a")?,
+                break_cause_b: break_cause(r, "break_cause_b", "br_b")?,
+                comments: r.try_get("comments")?,
+                inspector: check_from_row(r, "inspector_user_name", |r| {
+                    User::from_row_prefixed(r, "inspector")
+                })?,
+                inspection_time: r.try_get("inspection_time")?,
+                attachments: vec![],
+            })
+        }
+
+        fn machine_break_from_row(r: &DbRow) -> anyhow::Result<MachineBreak> {
+            Ok(MachineBreak {
+                id: r.try_get("id")?,
+                creation_time: r.try_get("creation_time")?,
+                inspection_flag: r.try_get("inspection_flag")?,
+                break_flag: r.try_get("break_flag")?,
+                break_spec: r.try_get("break_spec")?,
+                breakpoint_b: get_decimal(r, "breakpoint_b")?,
+                breakpoint_a: breakpoint_a(r)?,
+                break_cause_a: break_cause(r, "break_cause_a", "br_a")?,
+                break_cause_b: break_cause(r, "break_cause_b", "br_b")?,
+            })
+        }
+
+        #[async_trait]
+        impl InspectionRepository for $repository {
+            async fn create(&self, form: InspectionForm) -> anyhow::Result<i64> {
+                let mut tx = self.db.begin().await?;
+                let version = next_version(&mut tx).await?;
+                let id = insert(&mut tx, form).await?;
+                set_version(&mut tx, id, version).await?;
+                tx.commit().await?;
+                Ok(id)
+            }
+
+            async fn create_once(
+                &self,
+                key: &str,
+                window: Duration,
+                form: InspectionForm,
+            ) -> anyhow::Result<(i64, bool)> {
+                let now = $crate::timestamp_secs() as i64;
+                let expired = now - window.as_secs() as i64;
+                let mut tx = self.db.begin().await?;
+                // also makes retries racing each other wait for the first one
+                let version = next_version(&mut tx).await?;
+                let created = sqlx::query($sql!("inspection-idempotency-get"))
+                    .bind(key)
+                    .bind(expired)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+                if let Some(r) = created {
+                    return Ok((r.try_get("inspection_id")?, false));
+                }
+                sqlx::query($sql!("inspection-idempotency-prune"))
+                    .bind(expired)
+                    .execute(&mut *tx)
+                    .await?;
+                let id = insert(&mut tx, form).await?;
+                set_version(&mut tx, id, version).await?;
+                sqlx::query($sql!("inspection-idempotency-insert"))
+                    .bind(key)
+                    .bind(id)
+                    .bind(now)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+                Ok((id, true))
+            }
+
+            async fn update(&self, id: i64, form: InspectionForm) -> anyhow::Result<bool> {
+                let mut tx = self.db.begin().await?;
+                let version = next_version(&mut tx).await?;
+                let updated = update(&mut tx, id, form).await?;
+                if updated {
+                    set_version(&mut tx, id, version).await?;
+                    tx.commit().await?;
+                }
+                Ok(updated)
+            }
+
+            async fn search(&self, query: &SearchQuery) -> anyhow::Result<Vec<InspectionSummary>> {
+                let offset = query.offset.unwrap_or_default() as i64;
+                let limit = query.limit.unwrap_or(100) as i64;
+
+                let mut sql_query = sqlx::query($sql!("inspection-search"));
+                sql_query = sql_query.bind(query.stage as i32);
+                for _ in 0..11 {
+                    sql_query = sql_query.bind(&query.filter);
+                }
+                // limit, offset
+                let sql_query = sql_query.bind(limit).bind(offset);
+                let mut stream = sql_query.fetch(&self.db);
+                let mut collected = vec![];
+                while let Some(row) = stream.try_next().await? {
+                    let summary = InspectionSummary::from_row(&row)?;
+                    let summary = InspectionSummary {
+                        // `br_b` joins on `breakreasona` too
+                        break_cause_a: break_cause(&row, "br_a", "br_a")?,
+                        break_cause_b: break_cause(&row, "br_a", "br_b")?,
+                        ..summary
+                    };
+                    collected.push(summary);
+                }
+                Ok(collected)
+            }
+
+            async fn details(&self, id: i64) -> anyhow::Result<Option<InspectionDetails>> {
+                let r = sqlx::query($sql!("inspection-details"))
+                    .bind(id)
+                    .fetch_optional(&self.db)
+                    .await?;
+                let Some(r) = r else {
+                    return Ok(None);
+                };
+                Ok(Some(InspectionDetails {
+                    attachments: self.list(id).await?,
+                    ..details_from_row(&r)?
+                }))
+            }
+
+            async fn count(&self) -> anyhow::Result<i64> {
+                let r = sqlx::query($sql!("inspection-count"))
+                    .fetch_one(&self.db)
+                    .await?;
+                Ok(r.try_get("c")?)
+            }
+
+            async fn exists(&self, id: i64) -> anyhow::Result<bool> {
+                let r = sqlx::query($sql!("inspection-exists"))
+                    .bind(id)
+                    .fetch_one(&self.db)
+                    .await?;
+                Ok(r.try_get::<i64, _>("c")? > 0)
+            }
+
+            async fn set_deleted(&self, id: i64, deleted: bool) -> anyhow::Result<bool> {
+                let mut tx = self.db.begin().await?;
+                let version = next_version(&mut tx).await?;
+                let result = sqlx::query($sql!("inspection-set-deleted"))
+                    .bind(deleted as i32)
+                    .bind(id)
+                    .bind(deleted as i32)
+                    .execute(&mut *tx)
+                    .await?;
+                if result.rows_affected() == 0 {
+                    return Ok(false);
+                }
+                set_version(&mut tx, id, version).await?;
+                tx.commit().await?;
+                Ok(true)
+            }
+
+            async fn serial(&self) -> anyhow::Result<i32> {
+                let row = sqlx::query($sql!("inspection-counter"))
+                    .fetch_one(&self.db)
+                    .await?;
+                Ok(row.try_get("num")?)
+            }
+
+            async fn set_serial(&self, serial: i32) -> anyhow::Result<()> {
+                sqlx::query($sql!("counter-update"))
+                    .bind(serial)
+                    .execute(&self.db)
+                    .await?;
+                Ok(())
+            }
+
+            async fn export(&self, from: &str, to: &str) -> anyhow::Result<Vec<ExportRow>> {
+                Ok(sqlx::query_as($sql!("inspection-export"))
+                    .bind(from)
+                    .bind(to)
+                    .fetch_all(&self.db)
+                    .await?)
+            }
+
+            async fn batch(
+                &self,
+                ids: &[i64],
+                operation: &BatchOperation,
+            ) -> anyhow::Result<Vec<(BatchResult, Option<i32>)>> {
+                let now = local_time_now();
+                let mut tx = self.db.begin().await?;
+                // taken first for the lock; more are taken as needed
+                let mut version = Some(next_version(&mut tx).await?);
+                let mut results = vec![];
+                for &id in ids {
+                    let target: Option<BatchTarget> =
+                        sqlx::query_as($sql!("inspection-batch-target"))
+                            .bind(id)
+                            .fetch_optional(&mut *tx)
+                            .await?;
+                    let status = match plan(operation, target.as_ref()) {
+                        Ok(change) => {
+                            apply(&mut tx, id, change, &now).await?;
+                            let version = match version.take() {
+                                Some(x) => x,
+                                None => next_version(&mut tx).await?,
+                            };
+                            set_version(&mut tx, id, version).await?;
+                            BatchStatus::Done
+                        }
+                        Err(status) => status,
+                    };
+                    let device_code = target.map(|x| x.devicecode);
+                    results.push((BatchResult { id, status }, device_code));
+                }
+                tx.commit().await?;
+                Ok(results)
+            }
+
+            async fn import(
+                &self,
+                forms: Vec<InspectionForm>,
+                dry_run: bool,
+            ) -> anyhow::Result<Vec<Result<i64, String>>> {
+                let mut tx = self.db.begin().await?;
+                let mut results = vec![];
+                for form in forms {
+                    let result: anyhow::Result<i64> = try {
+                        let version = next_version(&mut tx).await?;
+                        let id = insert_imported(&mut tx, form).await?;
+                        set_version(&mut tx, id, version).await?;
+                        id
+                    };
+                    results.push(result.map_err(|e| e.to_string()));
+                }
+                if !dry_run && results.iter().all(|x| x.is_ok()) {
+                    tx.commit().await?;
+                }
+                Ok(results)
+            }
+
+            async fn history(
+                &self,
+                device_code: i32,
+                query: &HistoryQuery,
+            ) -> anyhow::Result<Option<MachineHistory>> {
+                let db = &self.db;
+                let limit = query.limit.unwrap_or(100) as i64;
+                let top = query.top.unwrap_or(5) as i64;
+
+                let Some(stage) = self.machine_stage(device_code).await? else {
+                    return Ok(None);
+                };
+
+                let rows = bind_window!(sqlx::query($sql!("machine-history")), device_code, query)
+                    .bind(limit)
+                    .fetch_all(db)
+                    .await?;
+                let breaks = rows
+                    .iter()
+                    .map(machine_break_from_row)
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+
+                let sql_query = bind_window!(
+                    sqlx::query_as($sql!("machine-top-causes")),
+                    device_code,
+                    query
+                );
+                let top_causes: Vec<CauseFrequency> = sql_query.bind(top).fetch_all(db).await?;
+
+                let sql_query = bind_window!(
+                    sqlx::query_as($sql!("machine-top-breakpoints")),
+                    device_code,
+                    query
+                );
+                let top_breakpoints: Vec<BreakpointFrequency> =
+                    sql_query.bind(top).fetch_all(db).await?;
+
+                let span =
+                    bind_window!(sqlx::query($sql!("machine-break-span")), device_code, query)
+                        .fetch_one(db)
+                        .await?;
+                let break_count: i64 = span.try_get("c")?;
+                let span_secs: Option<i64> = span.try_get("span_secs")?;
+
+                Ok(Some(MachineHistory {
+                    device_code,
+                    stage,
+                    break_count,
+                    mtbf_secs: mtbf_secs(break_count, span_secs),
+                    breaks,
+                    top_causes,
+                    top_breakpoints,
+                }))
+            }
+        }
+
+        #[async_trait]
+        impl MasterDataRepository for $repository {
+            async fn users(&self) -> anyhow::Result<Vec<User>> {
+                Ok(sqlx::query_as($sql!("users")).fetch_all(&self.db).await?)
+            }
+
+            async fn break_causes(&self) -> anyhow::Result<Vec<BreakCause>> {
+                Ok(sqlx::query_as($sql!("break-reasons"))
+                    .fetch_all(&self.db)
+                    .await?)
+            }
+
+            async fn breakpoints(&self) -> anyhow::Result<Vec<Breakpoint>> {
+                Ok(sqlx::query_as($sql!("breakpoints"))
+                    .fetch_all(&self.db)
+                    .await?)
+            }
+
+            async fn devices(&self, stage: i32) -> anyhow::Result<Vec<i32>> {
+                let devices = sqlx::query($sql!("devices"))
+                    .bind(stage)
+                    .fetch(&self.db)
+                    .map_ok(|x| x.get::<i32, _>(0))
+                    .try_collect()
+                    .await?;
+                Ok(devices)
+            }
+
+            async fn machine_stage(&self, device_code: i32) -> anyhow::Result<Option<i32>> {
+                let row = sqlx::query($sql!("machine"))
+                    .bind(device_code)
+                    .fetch_optional(&self.db)
+                    .await?;
+                Ok(row.map(|r| r.try_get("stage")).transpose()?)
+            }
+
+            async fn set_state(&self, kind: RefKind, id: i32, state: i32) -> anyhow::Result<bool> {
+                let sql = match kind {
+                    RefKind::User => $sql!("user-set-state"),
+                    RefKind::Cause => $sql!("break-reason-set-state"),
+                    RefKind::Breakpoint => $sql!("breakpoint-set-state"),
+                };
+                let result = sqlx::query(sql)
+                    .bind(state)
+                    .bind(id)
+                    .bind(state)
+                    .execute(&self.db)
+                    .await?;
+                Ok(result.rows_affected() > 0)
+            }
+        }
+
+        #[async_trait]
+        impl AttachmentRepository for $repository {
+            async fn insert(&self, attachment: NewAttachment) -> anyhow::Result<Attachment> {
+                let inserted = sqlx::query($sql!("attachment-insert"))
+                    .bind(attachment.inspection_id)
+                    .bind(attachment.filename)
+                    .bind(attachment.content_type)
+                    .bind(attachment.size)
+                    .bind(attachment.width)
+                    .bind(attachment.height)
+                    .bind(attachment.object_key)
+                    .bind(attachment.thumbnail_key)
+                    .execute(&self.db)
+                    .await?;
+                Ok(sqlx::query_as($sql!("attachment-get"))
+                    .bind($last_insert_id(&inserted))
+                    .fetch_one(&self.db)
+                    .await?)
+            }
+
+            async fn list(&self, inspection_id: i64) -> anyhow::Result<Vec<Attachment>> {
+                Ok(sqlx::query_as($sql!("attachment-list"))
+                    .bind(inspection_id)
+                    .fetch_all(&self.db)
+                    .await?)
+            }
+
+            async fn get(&self, id: i64) -> anyhow::Result<Option<Attachment>> {
+                Ok(sqlx::query_as($sql!("attachment-get"))
+                    .bind(id)
+                    .fetch_optional(&self.db)
+                    .await?)
+            }
+
+            async fn delete(&self, id: i64) -> anyhow::Result<()> {
+                sqlx::query($sql!("attachment-delete"))
+                    .bind(id)
+                    .execute(&self.db)
+                    .await?;
+                Ok(())
+            }
+        }
+
+        #[async_trait]
+        impl SyncRepository for $repository {
+            async fn push(&self, items: Vec<SyncItem>) -> anyhow::Result<Vec<SyncResult>> {
+                let mut tx = self.db.begin().await?;
+                // locks `sync_version` before reading anything
+                let mut version = Some(next_version(&mut tx).await?);
+                let mut results = vec![];
+                for item in items {
+                    let form = serde_json::to_string(&item.form)?;
+                    let synced: Option<SyncedForm> = sqlx::query_as($sql!("inspection-sync-get"))
+                        .bind(&item.client_id)
+                        .fetch_optional(&mut *tx)
+                        .await?;
+                    let current = match &synced {
+                        Some(x) => sqlx::query($sql!("inspection-version"))
+                            .bind(x.inspection_id)
+                            .fetch_optional(&mut *tx)
+                            .await?
+                            .map(|r| r.try_get::<i64, _>("version"))
+                            .transpose()?
+                            .unwrap_or_default(),
+                        None => 0,
+                    };
+                    let action = resolve(&item, &form, synced.as_ref().map(|x| (x, current)));
+                    let (id, status, version) = match action {
+                        SyncAction::Keep(id, status) => (id, status, current),
+                        SyncAction::Create => {
+                            let version = match version.take() {
+                                Some(x) => x,
+                                None => next_version(&mut tx).await?,
+                            };
+                            let id = insert(&mut tx, item.form).await?;
+                            set_version(&mut tx, id, version).await?;
+                            sqlx::query($sql!("inspection-sync-insert"))
+                                .bind(&item.client_id)
+                                .bind(id)
+                                .bind(&form)
+                                .bind(version)
+                                .execute(&mut *tx)
+                                .await?;
+                            (id, SyncStatus::Created, version)
+                        }
+                        SyncAction::Update(id) => {
+                            let version = match version.take() {
+                                Some(x) => x,
+                                None => next_version(&mut tx).await?,
+                            };
+                            update(&mut tx, id, item.form).await?;
+                            set_version(&mut tx, id, version).await?;
+                            sqlx::query($sql!("inspection-sync-update"))
+                                .bind(&form)
+                                .bind(version)
+                                .bind(&item.client_id)
+                                .execute(&mut *tx)
+                                .await?;
+                            (id, SyncStatus::Updated, version)
+                        }
+                    };
+                    results.push(SyncResult {
+                        client_id: item.client_id,
+                        id,
+                        status,
+                        version,
+                    });
+                }
+                tx.commit().await?;
+                Ok(results)
+            }
+
+            async fn changes(&self, since: i64, limit: u64) -> anyhow::Result<Changes> {
+                let rows = sqlx::query($sql!("inspection-changes"))
+                    .bind(since)
+                    .bind(limit as i64 + 1)
+                    .fetch_all(&self.db)
+                    .await?;
+                let more = rows.len() as u64 > limit;
+                let mut changes = vec![];
+                for r in rows.iter().take(limit as usize) {
+                    changes.push(InspectionChange {
+                        version: r.try_get("version")?,
+                        client_id: r.try_get("client_id")?,
+                        deleted: r.try_get::<i32, _>("deleteflag")? != 0,
+                        inspection: details_from_row(r)?,
+                    });
+                }
+                if let Some(last) = changes.last() {
+                    // of the whole page at once
+                    let attachments: Vec<Attachment> =
+                        sqlx::query_as($sql!("attachment-changes"))
+                            .bind(since)
+                            .bind(last.version)
+                            .fetch_all(&self.db)
+                            .await?;
+                    let index = changes
+                        .iter()
+                        .enumerate()
+                        .map(|(i, x)| (x.inspection.id, i))
+                        .collect::<HashMap<_, _>>();
+                    for attachment in attachments {
+                        if let Some(&i) = index.get(&attachment.inspection_id) {
+                            changes[i].inspection.attachments.push(attachment);
+                        }
+                    }
+                }
+                Ok(Changes {
+                    version: changes.last().map(|x| x.version).unwrap_or(since),
+                    changes,
+                    more,
+                })
+            }
+        }
+
+        #[async_trait]
+        impl Transfer for $repository {
+            async fn dump(&self) -> anyhow::Result<Dump> {
+                let db = &self.db;
+                Ok(Dump {
+                    users: self.users().await?,
+                    break_causes: self.break_causes().await?,
+                    breakpoints: self.breakpoints().await?,
+                    machines: sqlx::query_as($sql!("machines")).fetch_all(db).await?,
+                    serial: self.serial().await?,
+                    inspections: sqlx::query_as($sql!("inspection-dump"))
+                        .fetch_all(db)
+                        .await?,
+                    attachments: sqlx::query_as($sql!("attachment-dump"))
+                        .fetch_all(db)
+                        .await?,
+                    sync_version: sqlx::query($sql!("sync-version"))
+                        .fetch_one(db)
+                        .await?
+                        .try_get("version")?,
+                    versions: sqlx::query_as($sql!("inspection-version-dump"))
+                        .fetch_all(db)
+                        .await?,
+                    synced: sqlx::query_as($sql!("inspection-sync-dump"))
+                        .fetch_all(db)
+                        .await?,
+                })
+            }
+
+            async fn load(&self, dump: &Dump) -> anyhow::Result<()> {
+                let mut tx = self.db.begin().await?;
+                let count: i64 = sqlx::query($sql!("data-count"))
+                    .fetch_one(&mut *tx)
+                    .await?
+                    .try_get("c")?;
+                if count > 0 {
+                    return Err(anyhow::anyhow!("The target database is not empty"));
+                }
+
+                for x in &dump.users {
+                    sqlx::query($sql!("user-load"))
+                        .bind(x.id)
+                        .bind(&x.name)
+                        .bind(&x.user_type)
+                        .bind(x.enable_state)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                for x in &dump.break_causes {
+                    sqlx::query($sql!("break-reason-load"))
+                        .bind(x.id)
+                        .bind(&x.r#type)
+                        .bind(&x.cause)
+                        .bind(x.enable_state)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                for x in &dump.breakpoints {
+                    sqlx::query($sql!("breakpoint-load"))
+                        .bind(x.id)
+                        .bind(&x.breakpoint)
+                        .bind(x.enable_state)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                for &(number, stage) in &dump.machines {
+                    sqlx::query($sql!("machine-load"))
+                        .bind(number)
+                        .bind(stage)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                // seeded by the first migration on SQLite
+                let updated = sqlx::query($sql!("counter-update"))
+                    .bind(dump.serial)
+                    .execute(&mut *tx)
+                    .await?;
+                if updated.rows_affected() == 0 {
+                    sqlx::query($sql!("counter-insert"))
+                        .bind(dump.serial)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                for x in &dump.inspections {
+                    sqlx::query($sql!("inspection-load"))
+                        .bind(x.id)
+                        .bind(x.creator)
+                        .bind(x.devicecode)
+                        .bind(&x.devicecategory)
+                        .bind(&x.creationtime)
+                        .bind(&x.spec)
+                        .bind(x.wirenum)
+                        .bind(&x.wiretype)
+                        .bind(&x.breakspec)
+                        .bind(&x.twbatchcode)
+                        .bind(&x.trbatchcode)
+                        .bind(&x.dlwarehouse)
+                        .bind(&x.tgproducttime)
+                        .bind(&x.breakflag)
+                        .bind(x.breakpointa)
+                        .bind(decimal_text(x.breakpointb.clone())?)
+                        .bind(&x.memo)
+                        .bind(x.breakreasona)
+                        .bind(x.breakreasonb)
+                        .bind(x.billflag)
+                        .bind(&x.inspector)
+                        .bind(&x.inspecttime)
+                        .bind(x.deleteflag)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                for x in &dump.attachments {
+                    sqlx::query($sql!("attachment-load"))
+                        .bind(x.id)
+                        .bind(x.inspection_id)
+                        .bind(&x.filename)
+                        .bind(&x.content_type)
+                        .bind(x.size)
+                        .bind(x.width)
+                        .bind(x.height)
+                        .bind(&x.object_key)
+                        .bind(&x.thumbnail_key)
+                        .bind(&x.creation_time)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                sqlx::query($sql!("sync-version-set"))
+                    .bind(dump.sync_version)
+                    .execute(&mut *tx)
+                    .await?;
+                for &(id, version) in &dump.versions {
+                    set_version(&mut tx, id, version).await?;
+                }
+                for x in &dump.synced {
+                    sqlx::query($sql!("inspection-sync-insert"))
+                        .bind(&x.client_id)
+                        .bind(x.inspection_id)
+                        .bind(&x.form)
+                        .bind(x.version)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                tx.commit().await?;
+                Ok(())
+            }
+        }
+    };
+}
+use sql_repository;
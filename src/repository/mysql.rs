@@ -0,0 +1,31 @@
+//! The repositories over the customer's MySQL database
+//!
+//! All the queries in `sqls` are written for MySQL first.
+
+use sqlx::mysql::MySqlQueryResult;
+
+use crate::MySqlPool;
+
+/// All the repositories over the customer's `breakInfo` schema
+#[derive(Clone)]
+pub struct MySqlRepository {
+    db: MySqlPool,
+}
+
+impl MySqlRepository {
+    pub fn new(db: MySqlPool) -> Self {
+        Self { db }
+    }
+}
+
+macro_rules! sql {
+    ($name:literal) => {
+        crate::include_sql!($name)
+    };
+}
+
+fn last_insert_id(result: &MySqlQueryResult) -> i64 {
+    result.last_insert_id() as i64
+}
+
+super::sql_repository!(MySqlRepository, sqlx::MySql, sql, last_insert_id);
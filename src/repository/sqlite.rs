@@ -0,0 +1,64 @@
+//! The repositories over a local SQLite file
+//!
+//! Queries in `sqls/sqlite` replace the ones using MySQL-only syntax; the
+//! rest are shared with [`super::mysql`].
+
+use sqlx::sqlite::SqliteQueryResult;
+use sqlx::SqlitePool;
+
+#[derive(Clone)]
+pub struct SqliteRepository {
+    db: SqlitePool,
+}
+
+impl SqliteRepository {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+}
+
+/// Picks the query in `sqls/sqlite` if there's one.
+macro_rules! sql {
+    ("attachment-changes") => {
+        crate::include_sql!("sqlite/attachment-changes")
+    };
+    ("attachment-dump") => {
+        crate::include_sql!("sqlite/attachment-dump")
+    };
+    ("attachment-get") => {
+        crate::include_sql!("sqlite/attachment-get")
+    };
+    ("attachment-insert") => {
+        crate::include_sql!("sqlite/attachment-insert")
+    };
+    ("attachment-list") => {
+        crate::include_sql!("sqlite/attachment-list")
+    };
+    ("counter-update") => {
+        crate::include_sql!("sqlite/counter-update")
+    };
+    ("inspection-export") => {
+        crate::include_sql!("sqlite/inspection-export")
+    };
+    ("inspection-post") => {
+        crate::include_sql!("sqlite/inspection-post")
+    };
+    ("inspection-search") => {
+        crate::include_sql!("sqlite/inspection-search")
+    };
+    ("inspection-version-set") => {
+        crate::include_sql!("sqlite/inspection-version-set")
+    };
+    ("machine-break-span") => {
+        crate::include_sql!("sqlite/machine-break-span")
+    };
+    ($name:literal) => {
+        crate::include_sql!($name)
+    };
+}
+
+fn last_insert_id(result: &SqliteQueryResult) -> i64 {
+    result.last_insert_rowid()
+}
+
+super::sql_repository!(SqliteRepository, sqlx::Sqlite, sql, last_insert_id);
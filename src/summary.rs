@@ -0,0 +1,303 @@
+//! Summaries of the breaks on a schedule, like at the end of every shift
+//!
+//! Each summary counts the inspections of a stage created within a period
+//! by cause, machine and wire type. They're written as HTML and CSV into
+//! `summary.dir`, and mailed through `summary.smtp`.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::hash::Hash;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use chrono::{DateTime, Local};
+use cron::Schedule;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use log::{error, info};
+
+use crate::config::{self, SmtpSecurity};
+use crate::repository::{ExportRow, InspectionRepository, MasterDataRepository, TIME_FORMAT};
+use crate::{mutex_lock, ApiContext, CONFIG};
+
+/// The scheduler wakes up at least this often, to pick up reloaded configs.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// Parses a `summary.schedule` time. The standard five fields of crontab
+/// get a `0` second prepended.
+pub fn parse_schedule(expression: &str) -> anyhow::Result<Schedule> {
+    let expression = match expression.split_whitespace().count() {
+        5 => format!("0 {expression}"),
+        _ => expression.to_string(),
+    };
+    Schedule::from_str(&expression).map_err(|e| anyhow!("Invalid schedule {}: {}", expression, e))
+}
+
+/// Counts of the inspections of a stage created within `from..to`
+pub struct Summary {
+    pub stage: u32,
+    /// In [`TIME_FORMAT`]
+    pub from: String,
+    pub to: String,
+    pub total: usize,
+    pub finalized: usize,
+    /// The most frequent first
+    pub by_cause: Vec<(String, usize)>,
+    pub by_machine: Vec<(i32, usize)>,
+    pub by_wire_type: Vec<(String, usize)>,
+}
+
+fn count<K: Hash + Eq + Ord>(keys: impl Iterator<Item = K>) -> Vec<(K, usize)> {
+    let mut counts = HashMap::<K, usize>::new();
+    for key in keys {
+        *counts.entry(key).or_default() += 1;
+    }
+    let mut counts = counts.into_iter().collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl Summary {
+    /// `rows` must be of the stage and the period already.
+    pub fn new(stage: u32, from: String, to: String, rows: &[&ExportRow]) -> Self {
+        let unfilled = |x: Option<&str>| x.unwrap_or("未填写").to_string();
+        Self {
+            stage,
+            from,
+            to,
+            total: rows.len(),
+            finalized: rows.iter().filter(|x| x.inspection_flag == 1).count(),
+            by_cause: count(rows.iter().map(|x| unfilled(x.cause()))),
+            by_machine: count(rows.iter().map(|x| x.device_code)),
+            by_wire_type: count(rows.iter().map(|x| unfilled(x.wire_type.as_deref()))),
+        }
+    }
+
+    fn sections(&self) -> [(&'static str, Vec<(String, usize)>); 3] {
+        let machines = self.by_machine.iter().map(|(x, n)| (x.to_string(), *n));
+        [
+            ("断线原因", self.by_cause.clone()),
+            ("机台号", machines.collect()),
+            ("线材类型", self.by_wire_type.clone()),
+        ]
+    }
+
+    /// Like `summary-1-20240301-0800.csv`, by the end of the period
+    pub fn filename(&self, extension: &str) -> String {
+        let end = self.to.replace(['-', ':'], "").replace(' ', "-");
+        format!("summary-{}-{}.{}", self.stage, &end[..end.len().min(13)], extension)
+    }
+
+    /// A fragment, to be put into [`html_page`]
+    pub fn html(&self) -> String {
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<h2>工序 {}</h2>\n<p>{} 至 {}：合计 {} 条；初检 {} 条，终检 {} 条</p>\n",
+            self.stage,
+            self.from,
+            self.to,
+            self.total,
+            self.total - self.finalized,
+            self.finalized
+        );
+        for (title, counts) in self.sections() {
+            if counts.is_empty() {
+                continue;
+            }
+            let _ = writeln!(html, "<table border=\"1\" cellspacing=\"0\" cellpadding=\"4\">");
+            let _ = writeln!(html, "<tr><th>{title}</th><th>条数</th></tr>");
+            for (name, n) in counts {
+                let _ = writeln!(html, "<tr><td>{}</td><td>{}</td></tr>", escape(&name), n);
+            }
+            let _ = writeln!(html, "</table>\n<br>");
+        }
+        html
+    }
+
+    /// `分类,名称,条数` rows, with a BOM so Excel detects UTF-8
+    pub fn csv(&self) -> anyhow::Result<Vec<u8>> {
+        let mut writer = csv::Writer::from_writer(b"\xEF\xBB\xBF".to_vec());
+        writer.write_record(["分类", "名称", "条数"])?;
+        let totals = [
+            ("合计", self.total),
+            ("初检", self.total - self.finalized),
+            ("终检", self.finalized),
+        ];
+        for (title, n) in totals {
+            writer.write_record([title, "", &n.to_string()])?;
+        }
+        for (title, counts) in self.sections() {
+            for (name, n) in counts {
+                writer.write_record([title, &name, &n.to_string()])?;
+            }
+        }
+        Ok(writer.into_inner()?)
+    }
+}
+
+/// A whole HTML document of the summaries
+pub fn html_page(title: &str, summaries: &[Summary]) -> String {
+    let body = summaries.iter().map(Summary::html).collect::<String>();
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         </head>\n<body>\n<h1>{title}</h1>\n{body}</body>\n</html>\n"
+    )
+}
+
+fn title(from: &str, to: &str) -> String {
+    format!("断线汇总 {from} 至 {to}")
+}
+
+/// Summaries of the stages, of the inspections created within `from..to`
+pub async fn summarize(
+    inspections: &dyn InspectionRepository,
+    master_data: &dyn MasterDataRepository,
+    stages: &[u32],
+    from: DateTime<Local>,
+    to: DateTime<Local>,
+) -> anyhow::Result<Vec<Summary>> {
+    let day = |x: &DateTime<Local>| x.format("%Y-%m-%d").to_string();
+    let mut rows = inspections.export(&day(&from), &day(&to)).await?;
+    let (from, to) = (from.format(TIME_FORMAT).to_string(), to.format(TIME_FORMAT).to_string());
+    rows.retain(|x| x.creation_time >= from && x.creation_time < to);
+
+    let mut summaries = Vec::new();
+    for &stage in stages {
+        let devices = master_data.devices(stage as i32).await?;
+        let rows = rows
+            .iter()
+            .filter(|x| devices.contains(&x.device_code))
+            .collect::<Vec<_>>();
+        summaries.push(Summary::new(stage, from.clone(), to.clone(), &rows));
+    }
+    Ok(summaries)
+}
+
+/// Writes the summaries into `dir` and mails them, as configured
+pub async fn deliver(config: &config::Summary, summaries: &[Summary]) -> anyhow::Result<()> {
+    let Some(first) = summaries.first() else {
+        return Ok(());
+    };
+    let title = title(&first.from, &first.to);
+
+    if let Some(dir) = &config.dir {
+        tokio::fs::create_dir_all(dir).await?;
+        for summary in summaries {
+            let page = html_page(&title, std::slice::from_ref(summary));
+            let dir = Path::new(dir);
+            tokio::fs::write(dir.join(summary.filename("html")), page).await?;
+            tokio::fs::write(dir.join(summary.filename("csv")), summary.csv()?).await?;
+        }
+    }
+
+    if let Some(smtp) = &config.smtp {
+        send_mail(smtp, &title, summaries).await?;
+    }
+    Ok(())
+}
+
+fn mailbox(address: &str) -> anyhow::Result<Mailbox> {
+    address
+        .parse()
+        .map_err(|e| anyhow!("Invalid mail address {}: {}", address, e))
+}
+
+async fn send_mail(smtp: &config::Smtp, title: &str, summaries: &[Summary]) -> anyhow::Result<()> {
+    let mut builder = Message::builder().from(mailbox(&smtp.from)?).subject(title);
+    for to in &smtp.to {
+        builder = builder.to(mailbox(to)?);
+    }
+    let mut body = MultiPart::mixed().singlepart(SinglePart::html(html_page(title, summaries)));
+    let csv = ContentType::parse("text/csv; charset=utf-8")?;
+    for summary in summaries {
+        let attachment = Attachment::new(summary.filename("csv"));
+        body = body.singlepart(attachment.body(summary.csv()?, csv.clone()));
+    }
+    let message = builder.multipart(body)?;
+
+    let mut transport = match smtp.security {
+        SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host),
+        SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)?,
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.host)?,
+    };
+    if let Some(port) = smtp.port {
+        transport = transport.port(port);
+    }
+    if let Some(username) = &smtp.username {
+        let password = smtp.password.clone().unwrap_or_default();
+        transport = transport.credentials(Credentials::new(username.clone(), password));
+    }
+    transport.build().send(message).await?;
+    Ok(())
+}
+
+/// Summarizes and delivers the period ending at `end`, which starts at the
+/// previous time of `schedule`.
+pub async fn run_once(
+    inspections: &dyn InspectionRepository,
+    master_data: &dyn MasterDataRepository,
+    config: &config::Summary,
+    schedule: &Schedule,
+    end: DateTime<Local>,
+) -> anyhow::Result<()> {
+    let start = schedule
+        .after(&end)
+        .next_back()
+        .ok_or_else(|| anyhow!("No time before {}", end))?;
+    let summaries = summarize(inspections, master_data, &config.stages, start, end).await?;
+    deliver(config, &summaries).await?;
+    info!("Delivered the summaries of {} to {}", start, end);
+    Ok(())
+}
+
+/// Runs `summary.schedule`, forever. Reloads of the config apply within
+/// [`MAX_SLEEP`].
+pub async fn run_schedule(api_context: ApiContext) {
+    let mut last = Local::now();
+    loop {
+        let config = mutex_lock!(CONFIG).summary.clone();
+        let schedules = config
+            .schedule
+            .iter()
+            .filter_map(|x| parse_schedule(x).map_err(|e| error!("{}", e)).ok())
+            .collect::<Vec<_>>();
+        let next = schedules.iter().filter_map(|x| x.after(&last).next()).min();
+        let sleep = match next {
+            Some(next) => (next - Local::now()).to_std().unwrap_or_default().min(MAX_SLEEP),
+            None => MAX_SLEEP,
+        };
+        tokio::time::sleep(sleep).await;
+
+        let now = Local::now();
+        for schedule in &schedules {
+            // only the latest one if several were missed, like on a suspend
+            let Some(end) = schedule.after(&last).take_while(|x| *x <= now).last() else {
+                continue;
+            };
+            let result = run_once(
+                api_context.inspections.as_ref(),
+                api_context.master_data.as_ref(),
+                &config,
+                schedule,
+                end,
+            )
+            .await;
+            if let Err(e) = result {
+                error!("Failed to deliver the summaries of {}: {}", end, e);
+            }
+        }
+        last = now;
+    }
+}
@@ -0,0 +1,41 @@
+//! Access to `POST /admin/reload`
+
+mod common;
+
+use std::net::SocketAddr;
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{header, Request, StatusCode};
+use czttgd_dao::CONFIG;
+
+use common::TestApp;
+
+async fn reload(app: &TestApp, peer: &str, token: Option<&str>) -> StatusCode {
+    let mut request = Request::post("/v1/admin/reload");
+    if let Some(token) = token {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+    }
+    let mut request = request.body(Body::empty()).unwrap();
+    let peer: SocketAddr = peer.parse().unwrap();
+    request.extensions_mut().insert(ConnectInfo(peer));
+    app.request(request).await.0
+}
+
+// one test, as the token is in the global config
+#[tokio::test]
+async fn restrict_reloads() {
+    let app = TestApp::new().await;
+
+    // without a token, only from the server itself
+    assert_eq!(reload(&app, "192.168.1.20:50000", None).await, StatusCode::FORBIDDEN);
+    assert_eq!(reload(&app, "127.0.0.1:50000", None).await, StatusCode::OK);
+    assert_eq!(reload(&app, "[::1]:50000", None).await, StatusCode::OK);
+
+    CONFIG.lock().unwrap().admin.token = Some("s3cret".into());
+    assert_eq!(reload(&app, "127.0.0.1:50000", None).await, StatusCode::FORBIDDEN);
+    let wrong = reload(&app, "192.168.1.20:50000", Some("s3cre")).await;
+    assert_eq!(wrong, StatusCode::FORBIDDEN);
+    let right = reload(&app, "192.168.1.20:50000", Some("s3cret")).await;
+    assert_eq!(right, StatusCode::OK);
+}
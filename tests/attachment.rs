@@ -0,0 +1,77 @@
+//! Photos of inspections with `POST /inspection/:id/attachments`
+
+mod common;
+
+use std::io::Cursor;
+
+use axum::body::Body;
+use axum::http::{header, Method, Request};
+use futures::TryStreamExt;
+use image::{ImageFormat, RgbImage};
+use serde_json::Value;
+
+use common::{data, form, TestApp};
+
+fn png() -> Vec<u8> {
+    let mut png = Cursor::new(vec![]);
+    RgbImage::new(8, 8).write_to(&mut png, ImageFormat::Png).unwrap();
+    png.into_inner()
+}
+
+async fn upload(app: &TestApp, id: i64, files: &[(&str, Vec<u8>)]) -> Value {
+    let boundary = "attachment-boundary";
+    let mut body = vec![];
+    for (filename, content) in files {
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\n\
+                 Content-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\n\
+                 Content-Type: application/octet-stream\r\n\r\n"
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(content);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+    let request = Request::post(format!("/inspection/{id}/attachments"))
+        .header(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={boundary}"),
+        )
+        .body(Body::from(body))
+        .unwrap();
+    app.request(request).await.1
+}
+
+async fn stored_objects(app: &TestApp) -> usize {
+    let objects: Vec<_> = app.store().list(None).try_collect().await.unwrap();
+    objects.len()
+}
+
+#[tokio::test]
+async fn upload_images() {
+    let app = TestApp::new().await;
+    let id = data(app.send_form(Method::POST, "/inspection", &form(101, "1.0mm")).await);
+    let id = id.as_i64().unwrap();
+
+    let created = data(upload(&app, id, &[("a.png", png()), ("b.png", png())]).await);
+    assert_eq!(created.as_array().unwrap().len(), 2);
+    assert_eq!(created[0]["contentType"], "image/png");
+    // the images and their thumbnails
+    assert_eq!(stored_objects(&app).await, 4);
+}
+
+#[tokio::test]
+async fn discard_failed_uploads() {
+    let app = TestApp::new().await;
+    let id = data(app.send_form(Method::POST, "/inspection", &form(101, "1.0mm")).await);
+    let id = id.as_i64().unwrap();
+
+    // the first image is stored before the second one fails
+    let files = [("a.png", png()), ("b.png", b"not an image".to_vec())];
+    assert_eq!(upload(&app, id, &files).await["code"], 1);
+    let listed = data(app.get(&format!("/inspection/{id}/attachments")).await);
+    assert_eq!(listed, serde_json::json!([]));
+    assert_eq!(stored_objects(&app).await, 0);
+}
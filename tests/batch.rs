@@ -0,0 +1,111 @@
+//! Batch operations with `POST /inspection/batch`
+
+mod common;
+
+use axum::http::Method;
+use czttgd_api::{BatchOperation, BatchRequest, BatchResult, BatchStatus};
+use czttgd_dao::metrics::INSPECTIONS;
+use serde_json::Value;
+
+use common::{data, form, TestApp};
+
+async fn post(app: &TestApp) -> i64 {
+    let id = data(app.send_form(Method::POST, "/inspection", &form(101, "1.0mm")).await);
+    id.as_i64().unwrap()
+}
+
+async fn batch(app: &TestApp, ids: &[i64], operation: BatchOperation) -> Value {
+    let request = BatchRequest {
+        ids: ids.to_vec(),
+        operation,
+    };
+    app.send_json(Method::POST, "/inspection/batch", &request).await
+}
+
+async fn statuses(app: &TestApp, ids: &[i64], operation: BatchOperation) -> Vec<BatchStatus> {
+    let results: Vec<BatchResult> =
+        serde_json::from_value(data(batch(app, ids, operation).await)).unwrap();
+    assert_eq!(results.iter().map(|x| x.id).collect::<Vec<_>>(), ids);
+    results.into_iter().map(|x| x.status).collect()
+}
+
+async fn details(app: &TestApp, id: i64) -> Value {
+    data(app.get(&format!("/inspection/{id}/details")).await)
+}
+
+fn finalize(cause: i32) -> BatchOperation {
+    BatchOperation::Finalize {
+        cause,
+        inspector: "王检".into(),
+    }
+}
+
+#[tokio::test]
+async fn finalize_and_reassign_cause() {
+    let app = TestApp::new().await;
+    let (a, b) = (post(&app).await, post(&app).await);
+    use BatchStatus::*;
+
+    assert_eq!(statuses(&app, &[a, 1], finalize(1)).await, vec![Done, NotFound]);
+    let finalized = details(&app, a).await;
+    assert_eq!(finalized["inspectionFlag"], 1);
+    assert_eq!(finalized["breakCauseB"]["cause"], "导轮磨损");
+    assert_eq!(finalized["inspector"]["name"], "王检");
+    assert!(finalized["inspectionTime"].is_string());
+    assert_eq!(statuses(&app, &[a], finalize(1)).await, vec![Unchanged]);
+
+    // the final cause of 终检, the initial one of 初检
+    let reassign = BatchOperation::ReassignCause { cause: 1 };
+    assert_eq!(statuses(&app, &[a, b], reassign.clone()).await, vec![Unchanged, Done]);
+    assert_eq!(details(&app, b).await["breakCauseA"]["cause"], "导轮磨损");
+    let reassign = BatchOperation::ReassignCause { cause: 2 };
+    assert_eq!(statuses(&app, &[a], reassign).await, vec![Done]);
+    let reassigned = details(&app, a).await;
+    assert_eq!(reassigned["breakCauseA"]["cause"], "杂质");
+    assert_eq!(reassigned["breakCauseB"]["cause"], "杂质");
+}
+
+#[tokio::test]
+async fn delete_and_restore() {
+    let app = TestApp::new().await;
+    let (a, b) = (post(&app).await, post(&app).await);
+    use BatchStatus::*;
+    let deleted = || INSPECTIONS.with_label_values(&["deleted", "1"]).get();
+    let before = deleted();
+
+    assert_eq!(statuses(&app, &[a, b], BatchOperation::Delete).await, vec![Done, Done]);
+    assert_eq!(deleted() - before, 2);
+    assert_eq!(statuses(&app, &[a], finalize(1)).await, vec![Deleted]);
+    // stage 2, as the other tests update inspections of stage 1 meanwhile
+    let c = data(app.send_form(Method::POST, "/inspection", &form(201, "1.0mm")).await);
+    let c = c.as_i64().unwrap();
+    let updated = || INSPECTIONS.with_label_values(&["updated", "2"]).get();
+    let before = updated();
+    assert_eq!(statuses(&app, &[c], BatchOperation::Delete).await, vec![Done]);
+    assert_eq!(
+        statuses(&app, &[a, a, c], BatchOperation::Restore).await,
+        vec![Done, Unchanged, Done]
+    );
+    assert_eq!(updated() - before, 1);
+    let inspections = app.db().repositories().inspections;
+    // restored, so deletable again
+    assert!(inspections.set_deleted(a, true).await.unwrap());
+    assert!(!inspections.set_deleted(b, true).await.unwrap());
+}
+
+#[tokio::test]
+async fn reject_invalid_batches() {
+    let app = TestApp::new().await;
+    let id = post(&app).await;
+
+    let response = batch(&app, &[id], finalize(99)).await;
+    assert_eq!(response["message"], "No such break cause: 99");
+    let response = batch(&app, &[], BatchOperation::Delete).await;
+    assert_eq!(response["code"], 1);
+    let operation = BatchOperation::Finalize {
+        cause: 1,
+        inspector: " ".into(),
+    };
+    assert_eq!(batch(&app, &[id], operation).await["message"], "No inspector");
+    assert_eq!(details(&app, id).await["inspectionFlag"], 0);
+}
@@ -0,0 +1,29 @@
+//! Admin subcommands run with `czttgd <command>`
+
+mod common;
+
+use czttgd_dao::db::Database;
+use czttgd_dao::{cli, Command};
+
+use common::TestDb;
+
+fn all_migrations(db: &Database) -> Vec<i64> {
+    let migrator = match db {
+        Database::MySql(_) => &czttgd_dao::db::MIGRATOR,
+        Database::Sqlite(_) => &czttgd_dao::db::SQLITE_MIGRATOR,
+    };
+    migrator.iter().map(|m| m.version).collect()
+}
+
+#[tokio::test]
+async fn migrate_empty_database() {
+    let test_db = TestDb::unmigrated().await;
+    let db = &test_db.db;
+    assert_eq!(db.pending_migrations().await.unwrap(), all_migrations(db));
+
+    cli::run(Command::Check, &test_db.config()).await.unwrap();
+    cli::run(Command::Migrate, &test_db.config()).await.unwrap();
+    assert_eq!(db.pending_migrations().await.unwrap(), Vec::<i64>::new());
+    // nothing left to do
+    cli::run(Command::Migrate, &test_db.config()).await.unwrap();
+}
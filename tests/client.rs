@@ -0,0 +1,90 @@
+//! The typed client of `czttgd-client`, against the server on a local port
+
+mod common;
+
+use czttgd_client::{
+    ApiError, BatchOperation, BatchRequest, BatchStatus, ChangesQuery, Client, SearchQuery,
+};
+
+use common::{form, TestApp};
+
+async fn client(app: &TestApp) -> Client {
+    Client::new(format!("http://{}/", app.serve().await))
+}
+
+fn api_error(result: anyhow::Result<impl std::fmt::Debug>) -> ApiError {
+    result.unwrap_err().downcast::<ApiError>().unwrap()
+}
+
+#[tokio::test]
+async fn unwrap_responses() {
+    let app = TestApp::new().await;
+    let client = client(&app).await;
+
+    assert_eq!(client.health().await.unwrap(), "ok");
+    assert_eq!(client.ready().await.unwrap(), "ok");
+    assert_eq!(client.ping("hello").await.unwrap().text, "hello");
+    assert!(client.routes().await.unwrap().starts_with("# v1"));
+    assert!(client.openapi().await.unwrap()["paths"]["/users"].is_object());
+    assert_eq!(client.users().await.unwrap()[0].name, "张三");
+    assert_eq!(client.devices(1).await.unwrap(), vec![101, 102]);
+    assert_eq!(client.break_causes().await.unwrap().len(), 2);
+    assert_eq!(client.breakpoints().await.unwrap().len(), 2);
+
+    let counter = client.update_counter().await.unwrap();
+    let id = client.create_inspection(&form(101, "1.0mm")).await.unwrap();
+    assert_eq!(client.inspection_details(id).await.unwrap().device_code, 101);
+    client.update_inspection(id, &form(102, "1.2mm")).await.unwrap();
+    let details = client.inspection_details(id).await.unwrap();
+    assert_eq!(details.product_spec.as_deref(), Some("1.2mm"));
+    assert_eq!(client.update_counter().await.unwrap(), counter + 2);
+    assert_eq!(client.inspection_count().await.unwrap(), 1);
+    let query = SearchQuery {
+        filter: "".into(),
+        stage: 1,
+        limit: None,
+        offset: None,
+    };
+    assert_eq!(client.search_inspections(&query).await.unwrap()[0].id, id);
+
+    let request = BatchRequest {
+        ids: vec![id, 1],
+        operation: BatchOperation::Delete,
+    };
+    let results = client.batch_inspections(&request).await.unwrap();
+    assert_eq!(results[0].status, BatchStatus::Done);
+    assert_eq!(results[1].status, BatchStatus::NotFound);
+    let query = ChangesQuery {
+        since: Some(0),
+        limit: None,
+    };
+    let changes = client.inspection_changes(&query).await.unwrap();
+    assert_eq!(changes.changes.len(), 1);
+}
+
+#[tokio::test]
+async fn return_api_errors() {
+    let app = TestApp::new().await;
+    let client = client(&app).await;
+
+    let error = api_error(client.inspection_details(1).await);
+    assert_eq!(error.code, 1);
+    assert_eq!(error.message.as_deref(), Some("No such inspection: 1"));
+    assert_eq!(error.to_string(), "API error 1: No such inspection: 1");
+
+    // in place of the raw content
+    let error = api_error(client.inspection_report(1).await);
+    assert_eq!(error.code, 1);
+
+    let request = BatchRequest {
+        ids: vec![],
+        operation: BatchOperation::Restore,
+    };
+    let error = api_error(client.batch_inspections(&request).await);
+    assert_eq!(error.message.as_deref(), Some("Expected 1 to 1000 ids"));
+
+    // not an `ApiError` without a server
+    let unreachable = Client::new("http://127.0.0.1:1");
+    let error = unreachable.health().await.unwrap_err();
+    assert!(error.downcast_ref::<ApiError>().is_none());
+}
@@ -0,0 +1,310 @@
+//! In-memory fake of the repositories, seeded like `fixtures/data.sql`
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use anyhow::anyhow;
+use czttgd_api::{
+    BatchOperation, BatchResult, Changes, HistoryQuery, MachineHistory, SearchQuery, SyncItem,
+    SyncResult,
+};
+use czttgd_dao::handlers::{
+    Attachment, BreakCause, Breakpoint, InspectionDetails, InspectionForm, InspectionSummary, User,
+};
+use czttgd_dao::repository::{
+    AttachmentRepository, ExportRow, InspectionRepository, MasterDataRepository, NewAttachment,
+    SyncRepository,
+};
+use czttgd_dao::RefKind;
+
+struct Record {
+    id: i64,
+    form: InspectionForm,
+    deleted: bool,
+}
+
+pub struct MemoryRepository {
+    pub users: Vec<User>,
+    pub break_causes: Vec<BreakCause>,
+    pub breakpoints: Vec<Breakpoint>,
+    /// `(device code, stage)`
+    pub machines: Vec<(i32, i32)>,
+    inspections: Mutex<Vec<Record>>,
+    serial: Mutex<i32>,
+}
+
+impl Default for MemoryRepository {
+    fn default() -> Self {
+        let user = |id, name: &str| User {
+            id,
+            name: name.into(),
+            enable_state: 1,
+            user_type: "0".into(),
+        };
+        let cause = |id, name: &str| BreakCause {
+            id,
+            r#type: Some("设备".into()),
+            cause: Some(name.into()),
+            enable_state: 1,
+        };
+        let breakpoint = |id, name: &str| Breakpoint {
+            id,
+            breakpoint: Some(name.into()),
+            enable_state: 1,
+        };
+        Self {
+            users: vec![user(1, "张三"), user(2, "李四")],
+            break_causes: vec![cause(1, "导轮磨损"), cause(2, "杂质")],
+            breakpoints: vec![breakpoint(1, "进线口"), breakpoint(2, "出线口")],
+            machines: vec![(101, 1), (102, 1), (201, 2)],
+            inspections: Default::default(),
+            serial: Mutex::new(1),
+        }
+    }
+}
+
+impl MemoryRepository {
+    fn user(&self, id: i32) -> Option<User> {
+        self.users.iter().find(|x| x.id == id).cloned()
+    }
+
+    fn break_cause(&self, id: Option<i32>) -> Option<BreakCause> {
+        self.break_causes.iter().find(|x| Some(x.id) == id).cloned()
+    }
+
+    /// Mirrors the `LIKE` conditions of `inspection-search.sql` that don't
+    /// involve date formatting.
+    fn matches(&self, form: &InspectionForm, filter: &str) -> bool {
+        let cause = self.break_cause(form.break_cause_a).and_then(|x| x.cause);
+        [
+            self.user(form.creator).map(|x| x.name),
+            cause,
+            form.product_spec.clone(),
+            Some(form.creation_time.clone()),
+            Some(format!("{}号机台", form.device_code)),
+            form.comments.clone(),
+            form.wire_type.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|x| x.contains(filter))
+    }
+}
+
+#[async_trait]
+impl InspectionRepository for MemoryRepository {
+    async fn create(&self, form: InspectionForm) -> anyhow::Result<i64> {
+        let mut inspections = self.inspections.lock().unwrap();
+        let id = inspections.len() as i64 + 1;
+        inspections.push(Record {
+            id,
+            form,
+            deleted: false,
+        });
+        Ok(id)
+    }
+
+    async fn create_once(
+        &self,
+        _key: &str,
+        _window: Duration,
+        _form: InspectionForm,
+    ) -> anyhow::Result<(i64, bool)> {
+        Err(anyhow!("Not supported by the fake"))
+    }
+
+    async fn update(&self, id: i64, form: InspectionForm) -> anyhow::Result<bool> {
+        let mut inspections = self.inspections.lock().unwrap();
+        let Some(x) = inspections.iter_mut().find(|x| x.id == id) else {
+            return Ok(false);
+        };
+        x.form = form;
+        Ok(true)
+    }
+
+    async fn search(&self, query: &SearchQuery) -> anyhow::Result<Vec<InspectionSummary>> {
+        let inspections = self.inspections.lock().unwrap();
+        let collected = inspections
+            .iter()
+            .rev()
+            .filter(|x| !x.deleted)
+            .filter(|x| {
+                self.machines
+                    .iter()
+                    .any(|&(code, stage)| code == x.form.device_code && stage == query.stage as i32)
+            })
+            .filter(|x| self.matches(&x.form, &query.filter))
+            .skip(query.offset.unwrap_or_default() as usize)
+            .take(query.limit.unwrap_or(100) as usize)
+            .map(|x| InspectionSummary {
+                id: x.id,
+                device_code: x.form.device_code,
+                break_cause_a: self.break_cause(x.form.break_cause_a),
+                break_cause_b: None,
+                break_flag: x.form.break_flag,
+                break_spec: x.form.break_spec.clone(),
+                product_spec: x.form.product_spec.clone(),
+                creator: self.user(x.form.creator).unwrap(),
+                creation_time: x.form.creation_time.clone(),
+                inspection_flag: 0,
+            })
+            .collect();
+        Ok(collected)
+    }
+
+    async fn details(&self, id: i64) -> anyhow::Result<Option<InspectionDetails>> {
+        let inspections = self.inspections.lock().unwrap();
+        let Some(x) = inspections.iter().find(|x| x.id == id) else {
+            return Ok(None);
+        };
+        let form = x.form.clone();
+        Ok(Some(InspectionDetails {
+            id,
+            device_code: form.device_code,
+            device_category: form.device_category,
+            creator: self.user(form.creator).unwrap(),
+            creation_time: form.creation_time,
+            inspection_flag: 0,
+            product_spec: form.product_spec,
+            wire_num: form.wire_number,
+            wire_type: form.wire_type,
+            break_spec: form.break_spec,
+            wire_batch_code: form.wire_batch_code,
+            stick_batch_code: form.stick_batch_code,
+            warehouse: form.warehouse,
+            product_time: form.product_time,
+            break_flag: form.break_flag,
+            breakpoint_b: form.breakpoint_b.map(|x| x.parse::<BigDecimal>()).transpose()?,
+            breakpoint_a: self
+                .breakpoints
+                .iter()
+                .find(|x| Some(x.id) == form.breakpoint_a)
+                .cloned(),
+            break_cause_a: self.break_cause(form.break_cause_a),
+            break_cause_b: None,
+            comments: form.comments,
+            inspector: None,
+            inspection_time: None,
+            attachments: vec![],
+        }))
+    }
+
+    async fn count(&self) -> anyhow::Result<i64> {
+        Ok(self.inspections.lock().unwrap().len() as i64)
+    }
+
+    async fn exists(&self, id: i64) -> anyhow::Result<bool> {
+        let inspections = self.inspections.lock().unwrap();
+        Ok(inspections.iter().any(|x| x.id == id))
+    }
+
+    async fn set_deleted(&self, id: i64, deleted: bool) -> anyhow::Result<bool> {
+        let mut inspections = self.inspections.lock().unwrap();
+        match inspections.iter_mut().find(|x| x.id == id) {
+            Some(x) if x.deleted != deleted => {
+                x.deleted = deleted;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn serial(&self) -> anyhow::Result<i32> {
+        Ok(*self.serial.lock().unwrap())
+    }
+
+    async fn set_serial(&self, serial: i32) -> anyhow::Result<()> {
+        *self.serial.lock().unwrap() = serial;
+        Ok(())
+    }
+
+    async fn export(&self, _from: &str, _to: &str) -> anyhow::Result<Vec<ExportRow>> {
+        Err(anyhow!("Not supported by the fake"))
+    }
+
+    async fn batch(
+        &self,
+        _ids: &[i64],
+        _operation: &BatchOperation,
+    ) -> anyhow::Result<Vec<(BatchResult, Option<i32>)>> {
+        Err(anyhow!("Not supported by the fake"))
+    }
+
+    async fn import(
+        &self,
+        _forms: Vec<InspectionForm>,
+        _dry_run: bool,
+    ) -> anyhow::Result<Vec<Result<i64, String>>> {
+        Err(anyhow!("Not supported by the fake"))
+    }
+
+    async fn history(
+        &self,
+        _device_code: i32,
+        _query: &HistoryQuery,
+    ) -> anyhow::Result<Option<MachineHistory>> {
+        Err(anyhow!("Not supported by the fake"))
+    }
+}
+
+#[async_trait]
+impl MasterDataRepository for MemoryRepository {
+    async fn users(&self) -> anyhow::Result<Vec<User>> {
+        Ok(self.users.clone())
+    }
+
+    async fn break_causes(&self) -> anyhow::Result<Vec<BreakCause>> {
+        Ok(self.break_causes.clone())
+    }
+
+    async fn breakpoints(&self) -> anyhow::Result<Vec<Breakpoint>> {
+        Ok(self.breakpoints.clone())
+    }
+
+    async fn devices(&self, stage: i32) -> anyhow::Result<Vec<i32>> {
+        let devices = self.machines.iter().filter(|x| x.1 == stage);
+        Ok(devices.map(|x| x.0).collect())
+    }
+
+    async fn machine_stage(&self, device_code: i32) -> anyhow::Result<Option<i32>> {
+        let machine = self.machines.iter().find(|x| x.0 == device_code);
+        Ok(machine.map(|x| x.1))
+    }
+
+    async fn set_state(&self, _kind: RefKind, _id: i32, _state: i32) -> anyhow::Result<bool> {
+        Err(anyhow!("Not supported by the fake"))
+    }
+}
+
+/// No attachments at all; uploads fail.
+#[async_trait]
+impl AttachmentRepository for MemoryRepository {
+    async fn insert(&self, _attachment: NewAttachment) -> anyhow::Result<Attachment> {
+        Err(anyhow!("Not supported by the fake"))
+    }
+
+    async fn list(&self, _inspection_id: i64) -> anyhow::Result<Vec<Attachment>> {
+        Ok(vec![])
+    }
+
+    async fn get(&self, _id: i64) -> anyhow::Result<Option<Attachment>> {
+        Ok(None)
+    }
+
+    async fn delete(&self, _id: i64) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SyncRepository for MemoryRepository {
+    async fn push(&self, _items: Vec<SyncItem>) -> anyhow::Result<Vec<SyncResult>> {
+        Err(anyhow!("Not supported by the fake"))
+    }
+
+    async fn changes(&self, _since: i64, _limit: u64) -> anyhow::Result<Changes> {
+        Err(anyhow!("Not supported by the fake"))
+    }
+}
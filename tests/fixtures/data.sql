@@ -0,0 +1,19 @@
+INSERT INTO tt_user (userid, name, usertype, enablestate)
+VALUES (1, '张三', '0', 1),
+       (2, '李四', '1', 1);
+
+INSERT INTO tt_machine (machinenumber, stage)
+VALUES (101, 1),
+       (102, 1),
+       (201, 2);
+
+INSERT INTO tt_breakreason (breakreasonid, reasontype, breakreason, enablestate)
+VALUES (1, '设备', '导轮磨损', 1),
+       (2, '原料', '杂质', 1);
+
+INSERT INTO tt_breakpoint (breakpointid, breakpoint, enablestate)
+VALUES (1, '进线口', 1),
+       (2, '出线口', 1);
+
+INSERT INTO tt_number (serialNum)
+VALUES (1);
@@ -0,0 +1,166 @@
+#!/usr/bin/env python3
+"""Writes report-font.ttf, the font of tests/report.rs.
+
+A TrueType font covering printable ASCII and the Chinese characters of the
+reports, the master data and the test forms. The glyphs are boxes, as only
+the coverage matters. Run it again after adding characters to the reports:
+
+    python3 tests/fixtures/report-font.py
+"""
+
+import re
+import struct
+from pathlib import Path
+
+ROOT = Path(__file__).resolve().parents[2]
+SOURCES = ["src/report.rs", "src/import.rs", "tests/fixtures/data.sql", "tests/common/mod.rs"]
+NAME = "CzttgdReportTest"
+UNITS_PER_EM = 1000
+ASCENDER = 880
+DESCENDER = -120
+
+
+def characters():
+    chars = {chr(x) for x in range(0x21, 0x7F)}
+    for source in SOURCES:
+        text = (ROOT / source).read_text(encoding="utf-8")
+        chars.update(x for x in text if ord(x) > 0x7F and ord(x) <= 0xFFFF and x.isprintable())
+    return sorted(chars)
+
+
+def box(x_min, y_min, x_max, y_max, stroke):
+    """A frame: the outer contour clockwise, the inner one counterclockwise"""
+    outer = [(x_min, y_min), (x_min, y_max), (x_max, y_max), (x_max, y_min)]
+    inner = [
+        (x_min + stroke, y_min + stroke),
+        (x_max - stroke, y_min + stroke),
+        (x_max - stroke, y_max - stroke),
+        (x_min + stroke, y_max - stroke),
+    ]
+    points = outer + inner
+    data = struct.pack(">hhhhh", 2, x_min, y_min, x_max, y_max)
+    data += struct.pack(">HH", 3, 7)
+    data += struct.pack(">H", 0)
+    data += bytes([0x01] * len(points))
+    previous = 0
+    for x, _ in points:
+        data += struct.pack(">h", x - previous)
+        previous = x
+    previous = 0
+    for _, y in points:
+        data += struct.pack(">h", y - previous)
+        previous = y
+    return data
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(f">{len(data) // 4}I", data)) & 0xFFFFFFFF
+
+
+def cmap(mapping):
+    """Format 4, one segment per character"""
+    codes = sorted(mapping) + [0xFFFF]
+    glyphs = [mapping[x] for x in codes[:-1]] + [0]
+    count = len(codes)
+    search = 2 ** (count.bit_length() - 1)
+    subtable = struct.pack(
+        ">HHHHHHH",
+        4,
+        0,
+        0,
+        count * 2,
+        search * 2,
+        search.bit_length() - 1,
+        count * 2 - search * 2,
+    )
+    subtable += struct.pack(f">{count}H", *codes)
+    subtable += struct.pack(">H", 0)
+    subtable += struct.pack(f">{count}H", *codes)
+    deltas = [(glyph - code) & 0xFFFF if code != 0xFFFF else 1 for code, glyph in zip(codes, glyphs)]
+    subtable += struct.pack(f">{count}H", *deltas)
+    subtable += struct.pack(f">{count}H", *([0] * count))
+    subtable = subtable[:2] + struct.pack(">H", len(subtable)) + subtable[4:]
+    return struct.pack(">HHHHI", 0, 1, 3, 1, 12) + subtable
+
+
+def name():
+    records = {1: NAME, 2: "Regular", 3: NAME, 4: NAME, 6: NAME}
+    strings = b""
+    entries = b""
+    for name_id, value in records.items():
+        encoded = value.encode("utf-16-be")
+        entries += struct.pack(">HHHHHH", 3, 1, 0x409, name_id, len(encoded), len(strings))
+        strings += encoded
+    header = struct.pack(">HHH", 0, len(records), 6 + 12 * len(records))
+    return header + entries + strings
+
+
+def font():
+    chars = characters()
+    # .notdef, space, then the rest
+    glyphs = [box(50, 0, 450, 700, 50), b""]
+    advances = [500, 500]
+    mapping = {0x20: 1, 0x3000: 1}
+    for c in chars:
+        wide = ord(c) > 0xFF
+        advance = 1000 if wide else 500
+        glyphs.append(box(60, -80, advance - 60, 800, 60) if wide else box(60, 0, 440, 700, 50))
+        advances.append(advance)
+        mapping[ord(c)] = len(glyphs) - 1
+
+    glyf = b""
+    offsets = []
+    for glyph in glyphs:
+        offsets.append(len(glyf))
+        glyf += glyph + b"\0" * (-len(glyph) % 4)
+    offsets.append(len(glyf))
+    loca = struct.pack(f">{len(offsets)}I", *offsets)
+
+    count = len(glyphs)
+    tables = {
+        b"OS/2": struct.pack(
+            ">HhHHHhhhhhhhhhhh10sIIII4sHHHhhhHHII",
+            1, 700, 400, 5, 0, 650, 700, 0, 140, 650, 700, 0, 480, 50, 250, 0,
+            bytes([2, 11, 5, 0, 0, 0, 0, 0, 0, 0]), 1, 0x0800_0000, 0, 0,
+            b"NONE", 0x40, 0x20, 0xFFFF, ASCENDER, DESCENDER, 0, ASCENDER, -DESCENDER, 1 | (1 << 18), 0,
+        ),
+        b"cmap": cmap(mapping),
+        b"glyf": glyf,
+        b"head": struct.pack(
+            ">IIIIHHqqhhhhHHhhh",
+            0x00010000, 0x00010000, 0, 0x5F0F3CF5, 0b1011, UNITS_PER_EM, 0, 0,
+            0, DESCENDER, 1000, ASCENDER, 0, 8, 2, 1, 0,
+        ),
+        b"hhea": struct.pack(
+            ">IhhhHhhhhhhhhhhhH",
+            0x00010000, ASCENDER, DESCENDER, 0, 1000, 0, 0, 940, 1, 0, 0, 0, 0, 0, 0, 0, count,
+        ),
+        b"hmtx": b"".join(struct.pack(">Hh", advance, 50) for advance in advances),
+        b"loca": loca,
+        b"maxp": struct.pack(">IHHHHHHHHHHHHHH", 0x00010000, count, 8, 2, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0),
+        b"name": name(),
+        b"post": struct.pack(">IIhhIIIII", 0x00030000, 0, -100, 50, 0, 0, 0, 0, 0),
+    }
+
+    search = 2 ** (len(tables).bit_length() - 1)
+    header = struct.pack(
+        ">IHHHH", 0x00010000, len(tables), search * 16, search.bit_length() - 1, len(tables) * 16 - search * 16
+    )
+    directory = b""
+    body = b""
+    offset = len(header) + 16 * len(tables)
+    head_offset = 0
+    for tag, data in sorted(tables.items()):
+        if tag == b"head":
+            head_offset = offset + len(body)
+        directory += struct.pack(">4sIII", tag, checksum(data), offset + len(body), len(data))
+        body += data + b"\0" * (-len(data) % 4)
+    data = bytearray(header + directory + body)
+    adjustment = (0xB1B0AFBA - checksum(bytes(data))) & 0xFFFFFFFF
+    data[head_offset + 8 : head_offset + 12] = struct.pack(">I", adjustment)
+    return bytes(data)
+
+
+if __name__ == "__main__":
+    (Path(__file__).parent / "report-font.ttf").write_bytes(font())
@@ -0,0 +1,63 @@
+-- The parts of the customer's `breakInfo` schema used by the server. Column
+-- types follow what the handlers decode; this is not a copy of the real DDL.
+
+CREATE TABLE tt_user
+(
+    userid      INT PRIMARY KEY,
+    name        VARCHAR(64) NOT NULL,
+    usertype    VARCHAR(8)  NOT NULL,
+    enablestate INT         NOT NULL
+);
+
+CREATE TABLE tt_machine
+(
+    machinenumber INT PRIMARY KEY,
+    stage         INT NOT NULL
+);
+
+CREATE TABLE tt_breakreason
+(
+    breakreasonid INT PRIMARY KEY,
+    reasontype    VARCHAR(64)  NULL,
+    breakreason   VARCHAR(255) NULL,
+    enablestate   INT          NOT NULL
+);
+
+CREATE TABLE tt_breakpoint
+(
+    breakpointid INT PRIMARY KEY,
+    breakpoint   VARCHAR(255) NULL,
+    enablestate  INT          NOT NULL
+);
+
+CREATE TABLE tt_number
+(
+    serialNum INT NOT NULL
+);
+
+CREATE TABLE tt_inspect
+(
+    id             BIGINT PRIMARY KEY,
+    creator        INT            NOT NULL,
+    devicecode     INT            NOT NULL,
+    devicecategory VARCHAR(64)    NOT NULL,
+    creationtime   VARCHAR(32)    NOT NULL,
+    spec           VARCHAR(64)    NULL,
+    wirenum        INT            NULL,
+    wiretype       VARCHAR(64)    NULL,
+    breakspec      VARCHAR(64)    NOT NULL,
+    twbatchcode    VARCHAR(64)    NULL,
+    trbatchcode    VARCHAR(64)    NULL,
+    dlwarehouse    VARCHAR(64)    NULL,
+    tgproducttime  VARCHAR(32)    NULL,
+    breakflag      CHAR(1)        NOT NULL,
+    breakpointa    INT            NULL,
+    breakpointb    DECIMAL(10, 2) NULL,
+    memo           VARCHAR(255)   NULL,
+    breakreasona   INT            NULL,
+    breakreasonb   INT            NULL,
+    billflag       INT            NOT NULL DEFAULT 0,
+    inspector      VARCHAR(64)    NULL,
+    inspecttime    VARCHAR(32)    NULL,
+    deleteflag     INT            NOT NULL DEFAULT 0
+);
@@ -0,0 +1,47 @@
+//! Liveness and readiness with `GET /health` and `GET /ready`
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use serde_json::Value;
+
+use common::memory::MemoryRepository;
+use common::{TestApp, TestDb};
+
+async fn get(app: &TestApp, uri: &str) -> (StatusCode, Value) {
+    app.request(Request::get(uri).body(Body::empty()).unwrap()).await
+}
+
+#[tokio::test]
+async fn up() {
+    let app = TestApp::new().await;
+    let (status, response) = get(&app, "/health").await;
+    assert_eq!((status, &response["data"]), (StatusCode::OK, &Value::from("ok")));
+    let (status, response) = get(&app, "/ready").await;
+    assert_eq!((status, &response["data"]), (StatusCode::OK, &Value::from("ok")));
+}
+
+#[tokio::test]
+async fn database_down() {
+    let app = TestApp::in_memory(Arc::new(MemoryRepository::default()));
+    // alive all the same
+    assert_eq!(get(&app, "/health").await.0, StatusCode::OK);
+    let (status, response) = get(&app, "/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response["code"], 1);
+}
+
+#[tokio::test]
+async fn migrations_pending() {
+    let app = TestApp::with_db(TestDb::unmigrated().await);
+    let (status, response) = get(&app, "/ready").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let message = response["message"].as_str().unwrap();
+    assert!(message.starts_with("Pending migrations: [2026"), "{message}");
+
+    app.db().migrate().await.unwrap();
+    assert_eq!(get(&app, "/ready").await.0, StatusCode::OK);
+}
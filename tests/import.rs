@@ -0,0 +1,120 @@
+//! Importing spreadsheets with `POST /inspection/import`
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{header, Request};
+use czttgd_api::{ImportQuery, ImportReport};
+use czttgd_dao::metrics::INSPECTIONS;
+
+use common::{data, TestApp};
+
+const HEADER: &str = "创建人,机台号,机台类型,创建时间,断线规格,断点,原因,是否拉丝池内断线,班次";
+
+async fn import(app: &TestApp, csv: &str, dry_run: bool) -> ImportReport {
+    let query = ImportQuery {
+        dry_run,
+        map: Some("原因=breakCauseA".into()),
+    };
+    let boundary = "import-boundary";
+    let body = format!(
+        "--{boundary}\r\n\
+         Content-Disposition: form-data; name=\"file\"; filename=\"old.csv\"\r\n\
+         Content-Type: text/csv\r\n\r\n\
+         \u{FEFF}{csv}\r\n\
+         --{boundary}--\r\n"
+    );
+    let uri = format!("/inspection/import?{}", serde_urlencoded::to_string(&query).unwrap());
+    let request = Request::post(uri)
+        .header(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={boundary}"),
+        )
+        .body(Body::from(body))
+        .unwrap();
+    serde_json::from_value(data(app.request(request).await.1)).unwrap()
+}
+
+#[tokio::test]
+async fn import_csv() {
+    let app = TestApp::new().await;
+    let csv = format!(
+        "{HEADER}\n\
+         张三,101,拉丝机,2024/03/01 08:00,0.5,进线口,杂质,否,早班\n\
+         \n\
+         李四,201,拉丝机,2024-03-01 08:00:00,0.6,,导轮磨损,1,中班"
+    );
+    // stage 2, only imported here
+    let created = || INSPECTIONS.with_label_values(&["created", "2"]).get();
+    let before = created();
+    let report = import(&app, &csv, false).await;
+    assert_eq!(created() - before, 1);
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(report.rows, 2);
+    assert_eq!(report.imported, 2);
+    // the same second, so the serials follow
+    assert_eq!(report.ids[0] % 1000, 1);
+    assert_eq!(report.ids[1], report.ids[0] + 1);
+
+    let details = data(app.get(&format!("/inspection/{}/details", report.ids[0])).await);
+    assert_eq!(details["creator"]["name"], "张三");
+    assert_eq!(details["creationTime"], "2024-03-01 08:00:00");
+    assert_eq!(details["breakpointA"]["breakpoint"], "进线口");
+    assert_eq!(details["breakCauseA"]["cause"], "杂质");
+    let details = data(app.get(&format!("/inspection/{}/details", report.ids[1])).await);
+    assert_eq!(details["breakFlag"], true);
+    assert_eq!(details["breakpointA"], serde_json::Value::Null);
+
+    // live ids keep their serial
+    let inspections = app.db().repositories().inspections;
+    assert_eq!(inspections.serial().await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn import_reports_errors_by_row() {
+    let app = TestApp::new().await;
+    let csv = format!(
+        "{HEADER}\n\
+         张三,101,拉丝机,2024-03-01 08:00:00,0.5,进线口,杂质,否,\n\
+         王五,999,拉丝机,yesterday,0.5,,,,\n\
+         张三,abc,拉丝机,2024-03-01 09:00:00,,,没有这个原因,也许,"
+    );
+    let report = import(&app, &csv, false).await;
+    let errors = report
+        .errors
+        .iter()
+        .map(|x| (x.row, x.column.clone().unwrap_or_default(), x.message.clone()))
+        .collect::<Vec<_>>();
+    let error = |row, column: &str, message: &str| (row, column.to_string(), message.to_string());
+    assert_eq!(
+        errors,
+        vec![
+            error(3, "创建人", "No such name: 王五"),
+            error(3, "机台号", "No such machine: 999"),
+            error(3, "创建时间", "Invalid time: yesterday"),
+            error(4, "机台号", "Not an integer: abc"),
+            error(4, "断线规格", "Required"),
+            error(4, "是否拉丝池内断线", "Not a yes or no: 也许"),
+            error(4, "原因", "No such name: 没有这个原因"),
+        ]
+    );
+    assert_eq!(report.imported, 0);
+    assert_eq!(data(app.get("/inspection/count").await), 0);
+
+    let report = import(&app, "机台号,创建时间\n101,2024-03-01 08:00:00", false).await;
+    assert_eq!(report.errors.len(), 3);
+    assert_eq!(report.errors[0].row, 1);
+    assert_eq!(report.errors[0].message, "Missing column: 创建人 (creator)");
+}
+
+#[tokio::test]
+async fn import_dry_run() {
+    let app = TestApp::new().await;
+    let csv = format!("{HEADER}\n张三,101,拉丝机,2024-03-01 08:00:00,0.5,进线口,杂质,否,");
+    let report = import(&app, &csv, true).await;
+    assert!(report.dry_run);
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(report.imported, 1);
+    assert!(report.ids.is_empty());
+    assert_eq!(data(app.get("/inspection/count").await), 0);
+}
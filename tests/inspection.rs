@@ -0,0 +1,208 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::http::{header, Method, Request};
+use czttgd_api::SearchQuery;
+use czttgd_dao::handlers::InspectionForm;
+use serde_json::Value;
+
+use common::{data, form, TestApp};
+
+async fn post(app: &TestApp, form: &InspectionForm) -> i64 {
+    let id = data(app.send_form(Method::POST, "/inspection", form).await);
+    id.as_i64().unwrap()
+}
+
+/// `POST /inspection` with an `Idempotency-Key`
+async fn post_once(app: &TestApp, key: &str, form: &InspectionForm) -> Value {
+    let request = Request::post("/inspection")
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .header("Idempotency-Key", key)
+        .body(Body::from(serde_urlencoded::to_string(form).unwrap()))
+        .unwrap();
+    app.request(request).await.1
+}
+
+async fn search(app: &TestApp, stage: u32, filter: &str) -> Vec<i64> {
+    let query = SearchQuery {
+        filter: filter.into(),
+        stage,
+        ..Default::default()
+    };
+    search_with(app, &query)
+        .await
+        .iter()
+        .map(|x| x["id"].as_i64().unwrap())
+        .collect()
+}
+
+async fn search_with(app: &TestApp, query: &SearchQuery) -> Vec<Value> {
+    let query = serde_urlencoded::to_string(query).unwrap();
+    let response = app.get(&format!("/inspection/search?{query}")).await;
+    data(response).as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn post_new_and_query_details() {
+    let app = TestApp::new().await;
+    let id = post(&app, &form(101, "1.0mm")).await;
+
+    let details = data(app.get(&format!("/inspection/{id}/details")).await);
+    assert_eq!(details["id"], id);
+    assert_eq!(details["deviceCode"], 101);
+    assert_eq!(details["creator"]["name"], "张三");
+    assert_eq!(details["productSpec"], "1.0mm");
+    assert_eq!(details["wireNum"], 3);
+    assert_eq!(details["breakFlag"], false);
+    assert_eq!(details["breakpointA"]["breakpoint"], "进线口");
+    assert_eq!(details["breakCauseA"]["cause"], "杂质");
+    assert_eq!(details["breakCauseB"], Value::Null);
+    assert_eq!(details["inspectionFlag"], 0);
+    assert_eq!(details["attachments"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn query_details_of_missing_inspection() {
+    let app = TestApp::new().await;
+    let response = app.get("/inspection/1/details").await;
+    assert_eq!(response["code"], 1);
+}
+
+#[tokio::test]
+async fn update() {
+    let app = TestApp::new().await;
+    let id = post(&app, &form(101, "1.0mm")).await;
+
+    let mut changed = form(102, "2.0mm");
+    changed.break_flag = true;
+    changed.breakpoint_a = None;
+    changed.breakpoint_b = Some("12.5".into());
+    changed.break_cause_a = Some(1);
+    data(app.send_form(Method::PUT, &format!("/inspection/{id}"), &changed).await);
+
+    let details = data(app.get(&format!("/inspection/{id}/details")).await);
+    assert_eq!(details["deviceCode"], 102);
+    assert_eq!(details["productSpec"], "2.0mm");
+    assert_eq!(details["breakFlag"], true);
+    assert_eq!(details["breakpointA"], Value::Null);
+    assert_eq!(details["breakpointB"], "12.50");
+    assert_eq!(details["breakCauseA"]["cause"], "导轮磨损");
+}
+
+#[tokio::test]
+async fn search_by_stage_and_filter() {
+    let app = TestApp::new().await;
+    let a = post(&app, &form(101, "1.0mm")).await;
+    let b = post(&app, &form(102, "2.0mm")).await;
+    let c = post(&app, &form(201, "1.0mm")).await;
+
+    // the newest first
+    assert_eq!(search(&app, 1, "").await, vec![b, a]);
+    assert_eq!(search(&app, 2, "").await, vec![c]);
+    assert_eq!(search(&app, 1, "1.0mm").await, vec![a]);
+    assert_eq!(search(&app, 1, "102号机台").await, vec![b]);
+    assert_eq!(search(&app, 1, "张三").await, vec![b, a]);
+
+    let page = SearchQuery {
+        stage: 1,
+        limit: Some(1),
+        offset: Some(1),
+        ..Default::default()
+    };
+    assert_eq!(search_with(&app, &page).await[0]["id"], a);
+
+    let query = SearchQuery {
+        stage: 2,
+        ..Default::default()
+    };
+    let summary = &search_with(&app, &query).await[0];
+    assert_eq!(summary["deviceCode"], 201);
+    assert_eq!(summary["creator"]["name"], "张三");
+    assert_eq!(summary["breakCauseA"]["cause"], "杂质");
+}
+
+#[tokio::test]
+async fn search_skips_deleted() {
+    let app = TestApp::new().await;
+    let id = post(&app, &form(101, "1.0mm")).await;
+    let inspections = app.db().repositories().inspections;
+    assert!(inspections.set_deleted(id, true).await.unwrap());
+    assert!(search(&app, 1, "").await.is_empty());
+}
+
+#[tokio::test]
+async fn count() {
+    let app = TestApp::new().await;
+    assert_eq!(data(app.get("/inspection/count").await), 0);
+    post(&app, &form(101, "1.0mm")).await;
+    post(&app, &form(201, "1.0mm")).await;
+    assert_eq!(data(app.get("/inspection/count").await), 2);
+}
+
+#[tokio::test]
+async fn ids_end_with_the_serial() {
+    let app = TestApp::new().await;
+    let first = post(&app, &form(101, "1.0mm")).await;
+    let second = post(&app, &form(101, "1.0mm")).await;
+    assert_eq!(first % 1000, 1);
+    assert_eq!(second % 1000, 2);
+}
+
+#[tokio::test]
+async fn serial_wraps_after_999() {
+    let app = TestApp::new().await;
+    let inspections = app.db().repositories().inspections;
+    inspections.set_serial(999).await.unwrap();
+    let last = post(&app, &form(101, "1.0mm")).await;
+    let wrapped = post(&app, &form(101, "1.0mm")).await;
+    assert_eq!(last % 1000, 999);
+    assert_eq!(wrapped % 1000, 1);
+}
+
+#[tokio::test]
+async fn update_counter_increases_on_changes() {
+    let app = TestApp::new().await;
+    let before = app.get("/inspection/updatecounter").await.as_u64().unwrap();
+    let id = post(&app, &form(101, "1.0mm")).await;
+    data(app.send_form(Method::PUT, &format!("/inspection/{id}"), &form(101, "2.0mm")).await);
+    let after = app.get("/inspection/updatecounter").await.as_u64().unwrap();
+    // other tests in this binary may also change it
+    assert!(after >= before + 2);
+}
+
+#[tokio::test]
+async fn idempotency_key_replays() {
+    let app = TestApp::new().await;
+    let first = data(post_once(&app, "tablet-1/42", &form(101, "1.0mm")).await);
+    let replayed = data(post_once(&app, "tablet-1/42", &form(101, "1.0mm")).await);
+    assert_eq!(replayed, first);
+    assert_eq!(data(app.get("/inspection/count").await), 1);
+
+    let other = data(post_once(&app, "tablet-1/43", &form(101, "1.0mm")).await);
+    assert_ne!(other, first);
+    assert_eq!(data(app.get("/inspection/count").await), 2);
+
+    let response = post_once(&app, "", &form(101, "1.0mm")).await;
+    assert_eq!(response["message"], "Invalid Idempotency-Key");
+    let response = post_once(&app, &"k".repeat(256), &form(101, "1.0mm")).await;
+    assert_eq!(response["message"], "Invalid Idempotency-Key");
+}
+
+#[tokio::test]
+async fn idempotency_keys_expire() {
+    let app = TestApp::new().await;
+    let inspections = app.db().repositories().inspections;
+    let (first, created) = inspections
+        .create_once("key", Duration::ZERO, form(101, "1.0mm"))
+        .await
+        .unwrap();
+    assert!(created);
+    let (second, created) = inspections
+        .create_once("key", Duration::ZERO, form(101, "1.0mm"))
+        .await
+        .unwrap();
+    assert!(created);
+    assert_ne!(second, first);
+}
@@ -0,0 +1,223 @@
+//! Client log uploads with `POST /log`, and their management with
+//! `GET /logs`, `GET /log/:id` and `DELETE /log/:id`
+
+mod common;
+
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+use async_compression::tokio::write::{GzipEncoder, ZstdEncoder};
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{header, Request, StatusCode};
+use czttgd_dao::CONFIG;
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+
+use common::{data, TestApp};
+
+const MAX_SIZE: u64 = 1024;
+
+async fn gzip(content: &[u8]) -> Vec<u8> {
+    let mut encoder = GzipEncoder::new(vec![]);
+    encoder.write_all(content).await.unwrap();
+    encoder.shutdown().await.unwrap();
+    encoder.into_inner()
+}
+
+async fn zstd(content: &[u8]) -> Vec<u8> {
+    let mut encoder = ZstdEncoder::new(vec![]);
+    encoder.write_all(content).await.unwrap();
+    encoder.shutdown().await.unwrap();
+    encoder.into_inner()
+}
+
+async fn upload(app: &TestApp, device: &str, filename: &str, content: &[u8]) -> Value {
+    let boundary = "log-boundary";
+    let mut body = format!(
+        "--{boundary}\r\n\
+         Content-Disposition: form-data; name=\"device\"\r\n\r\n\
+         {device}\r\n\
+         --{boundary}\r\n\
+         Content-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(content);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    let request = Request::post("/log")
+        .header(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={boundary}"),
+        )
+        .body(Body::from(body))
+        .unwrap();
+    app.request(request).await.1
+}
+
+/// From the server itself, allowed without `admin.token`
+fn local(request: axum::http::request::Builder) -> Request<Body> {
+    let mut request = request.body(Body::empty()).unwrap();
+    let peer: SocketAddr = "127.0.0.1:50000".parse().unwrap();
+    request.extensions_mut().insert(ConnectInfo(peer));
+    request
+}
+
+async fn list(app: &TestApp, query: &str) -> Vec<Value> {
+    let (_, response) = app.request(local(Request::get(format!("/logs{query}")))).await;
+    data(response).as_array().unwrap().clone()
+}
+
+async fn download(app: &TestApp, id: &str) -> Vec<u8> {
+    let (status, headers, body) = app.request_raw(local(Request::get(format!("/log/{id}")))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::CONTENT_TYPE], "application/octet-stream");
+    body.to_vec()
+}
+
+async fn delete(app: &TestApp, id: &str) -> Value {
+    app.request(local(Request::delete(format!("/log/{id}")))).await.1
+}
+
+fn ids(logs: &[Value]) -> Vec<String> {
+    let mut ids = logs
+        .iter()
+        .map(|x| x["id"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    ids.sort();
+    ids
+}
+
+// one test, as the directory, the limit and the token are in the global
+// config
+#[tokio::test]
+async fn manage_uploads() {
+    let dir = std::env::temp_dir().join(format!("czttgd_log_{}", uuid::Uuid::new_v4()));
+    {
+        let mut config = CONFIG.lock().unwrap();
+        config.log_upload.dir = dir.to_str().unwrap().into();
+        config.log_upload.max_decompressed_size = MAX_SIZE;
+    }
+    let app = TestApp::new().await;
+    limit_decompressed_sizes(&app, &dir).await;
+    list_download_and_delete(&app).await;
+    restrict_access(&app).await;
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+async fn limit_decompressed_sizes(app: &TestApp, dir: &Path) {
+    let within = vec![b'a'; MAX_SIZE as usize];
+    let over = vec![b'a'; MAX_SIZE as usize + 1];
+
+    let mut uploaded = vec![];
+    for (filename, content) in [
+        ("app.log", within.clone()),
+        ("app.log.gz", gzip(&within).await),
+        ("app.log.zst", zstd(&within).await),
+    ] {
+        let id = data(upload(app, "tablet-1", filename, &content).await);
+        let id = id.as_str().unwrap().to_string();
+        assert_eq!(download(app, &id).await, within, "{filename}");
+        uploaded.push(id);
+    }
+
+    // compressed below the limit, but not once decompressed
+    for (filename, content) in [
+        ("app.log", over.clone()),
+        ("app.log.gz", gzip(&over).await),
+        ("app.log.zst", zstd(&over).await),
+    ] {
+        let response = upload(app, "tablet-1", filename, &content).await;
+        assert_eq!(response["code"], 1, "{filename}");
+        let message = response["message"].as_str().unwrap();
+        assert!(message.contains("exceeds the size limit of 1024 bytes"), "{message}");
+    }
+
+    // only the complete uploads are kept
+    let logs = list(app, "").await;
+    uploaded.sort();
+    assert_eq!(ids(&logs), uploaded);
+    assert_eq!(std::fs::read_dir(dir).unwrap().count(), 6);
+    assert_eq!(logs[0]["originalFilename"], "app.log");
+    assert_eq!(logs[0]["size"], MAX_SIZE);
+
+    for id in uploaded {
+        data(delete(app, &id).await);
+    }
+    assert_eq!(std::fs::read_dir(dir).unwrap().count(), 0);
+}
+
+async fn list_download_and_delete(app: &TestApp) {
+    let first = data(upload(app, "tablet-1", "first.log", b"first").await);
+    let first = first.as_str().unwrap().to_string();
+    // ids start with the received time in milliseconds
+    tokio::time::sleep(Duration::from_millis(2)).await;
+    let second = data(upload(app, "tablet-2", "second.log", b"second").await);
+    let second = second.as_str().unwrap().to_string();
+
+    // the newest first
+    let logs = list(app, "").await;
+    assert_eq!(logs.len(), 2);
+    assert_eq!(logs[0]["id"], second.as_str());
+    assert_eq!(logs[0]["device"], "tablet-2");
+    assert_eq!(logs[0]["originalFilename"], "second.log");
+    assert_eq!(logs[0]["size"], 6);
+    assert_eq!(ids(&list(app, "?device=tablet-1").await), vec![first.clone()]);
+    assert!(list(app, "?device=tablet-3").await.is_empty());
+
+    let request = local(Request::get(format!("/log/{first}")));
+    let (_, headers, body) = app.request_raw(request).await;
+    assert_eq!(
+        headers[header::CONTENT_DISPOSITION],
+        "attachment; filename=\"first.log\""
+    );
+    assert_eq!(&body[..], b"first");
+
+    data(delete(app, &first).await);
+    assert_eq!(ids(&list(app, "").await), vec![second.clone()]);
+    assert_eq!(delete(app, &first).await["code"], 1);
+    let (_, response) = app.request(local(Request::get(format!("/log/{first}")))).await;
+    assert_eq!(response["code"], 1);
+    // never outside the directory
+    assert_eq!(delete(app, "..%2Fescape").await["code"], 1);
+    data(delete(app, &second).await);
+}
+
+async fn restrict_access(app: &TestApp) {
+    let id = data(upload(app, "tablet-1", "app.log", b"content").await);
+    let id = id.as_str().unwrap().to_string();
+    let remote = |request: axum::http::request::Builder, token: Option<&str>| {
+        let mut request = match token {
+            Some(token) => request.header(header::AUTHORIZATION, format!("Bearer {token}")),
+            None => request,
+        }
+        .body(Body::empty())
+        .unwrap();
+        let peer: SocketAddr = "192.168.1.20:50000".parse().unwrap();
+        request.extensions_mut().insert(ConnectInfo(peer));
+        request
+    };
+    let status = |request| async move { app.request_raw(request).await.0 };
+
+    for token in [None, Some("s3cret")] {
+        let uri = format!("/log/{id}");
+        let forbidden = StatusCode::FORBIDDEN;
+        assert_eq!(status(remote(Request::get("/logs"), token)).await, forbidden);
+        assert_eq!(status(remote(Request::get(&uri), token)).await, forbidden);
+        assert_eq!(status(remote(Request::delete(&uri), token)).await, forbidden);
+    }
+    // uploads stay open to the tablets
+    let response = upload(app, "tablet-1", "app.log", b"content").await;
+    let other = data(response).as_str().unwrap().to_string();
+
+    CONFIG.lock().unwrap().admin.token = Some("s3cret".into());
+    assert_eq!(status(local(Request::get("/logs"))).await, StatusCode::FORBIDDEN);
+    let uri = format!("/log/{id}");
+    let token = Some("s3cret");
+    assert_eq!(status(remote(Request::get("/logs"), token)).await, StatusCode::OK);
+    assert_eq!(status(remote(Request::get(&uri), token)).await, StatusCode::OK);
+    assert_eq!(status(remote(Request::delete(&uri), token)).await, StatusCode::OK);
+    let uri = format!("/log/{other}");
+    assert_eq!(status(remote(Request::delete(&uri), token)).await, StatusCode::OK);
+}
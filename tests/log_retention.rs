@@ -0,0 +1,98 @@
+//! `log_upload.max_age_days` and `max_total_size`, applied by
+//! `run_log_retention`
+
+mod common;
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use axum::body::Body;
+use axum::http::{header, Request};
+use czttgd_dao::handlers::run_log_retention;
+use czttgd_dao::CONFIG;
+use serde_json::Value;
+
+use common::{data, TestApp};
+
+async fn upload(app: &TestApp, content: &[u8]) -> String {
+    let boundary = "log-boundary";
+    let mut body = format!(
+        "--{boundary}\r\n\
+         Content-Disposition: form-data; name=\"file\"; filename=\"app.log\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(content);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    let request = Request::post("/log")
+        .header(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={boundary}"),
+        )
+        .body(Body::from(body))
+        .unwrap();
+    let id = data(app.request(request).await.1);
+    // ids start with the received time in milliseconds
+    tokio::time::sleep(Duration::from_millis(2)).await;
+    id.as_str().unwrap().into()
+}
+
+/// Backdates the upload by `days`.
+fn age(dir: &Path, id: &str, days: u64) {
+    let path = dir.join(format!("{id}.json"));
+    let mut metadata: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    let time = SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60);
+    metadata["receivedTime"] = humantime::format_rfc3339_seconds(time).to_string().into();
+    std::fs::write(path, serde_json::to_vec(&metadata).unwrap()).unwrap();
+}
+
+fn kept(dir: &Path) -> Vec<String> {
+    let mut kept = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|x| x.unwrap().file_name().into_string().unwrap())
+        .filter_map(|x| x.strip_suffix(".json").map(String::from))
+        .collect::<Vec<_>>();
+    kept.sort();
+    kept
+}
+
+// one test, as the directory and the limits are in the global config
+#[tokio::test]
+async fn apply_retention() {
+    let dir = std::env::temp_dir().join(format!("czttgd_log_{}", uuid::Uuid::new_v4()));
+    CONFIG.lock().unwrap().log_upload.dir = dir.to_str().unwrap().into();
+    let app = TestApp::new().await;
+
+    let expired = upload(&app, &[b'a'; 100]).await;
+    let stuck = upload(&app, &[b'a'; 100]).await;
+    let oldest = upload(&app, &[b'a'; 100]).await;
+    let older = upload(&app, &[b'a'; 100]).await;
+    let newest = upload(&app, &[b'a'; 100]).await;
+    age(&dir, &expired, 8);
+    age(&dir, &stuck, 8);
+    // fails to be removed, but doesn't stop the others from being removed
+    let log = dir.join(format!("{stuck}.log"));
+    std::fs::remove_file(&log).unwrap();
+    std::fs::create_dir_all(log.join("content")).unwrap();
+
+    {
+        let mut config = CONFIG.lock().unwrap();
+        config.log_upload.max_age_days = Some(7);
+        config.log_upload.max_total_size = Some(200);
+    }
+    let retention = tokio::spawn(run_log_retention());
+    // applied right away, then hourly
+    for _ in 0..100 {
+        if kept(&dir).len() <= 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    retention.abort();
+
+    assert_eq!(kept(&dir), [older.clone(), newest.clone()]);
+    assert!(!dir.join(format!("{expired}.log")).exists());
+    assert!(!dir.join(format!("{oldest}.log")).exists());
+    assert!(dir.join(format!("{older}.log")).exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,65 @@
+//! Request IDs and access log lines, written by the middleware of
+//! `czttgd_dao::logging`
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Request, StatusCode};
+use axum::middleware;
+use czttgd_dao::config::{LogFormat, Logging};
+use czttgd_dao::logging::{self, REQUEST_ID_HEADER};
+use serde_json::Value;
+
+use common::TestApp;
+
+async fn health(app: &TestApp, request_id: Option<&str>) -> String {
+    let mut request = Request::get("/health");
+    if let Some(id) = request_id {
+        request = request.header(REQUEST_ID_HEADER, id);
+    }
+    let (status, headers, _) = app.request_raw(request.body(Body::empty()).unwrap()).await;
+    assert_eq!(status, StatusCode::OK);
+    headers[REQUEST_ID_HEADER].to_str().unwrap().into()
+}
+
+// one test, as the logger is global
+#[tokio::test]
+async fn tag_and_log_requests() {
+    let file = std::env::temp_dir().join(format!("czttgd_log_{}.log", uuid::Uuid::new_v4()));
+    logging::set_up_logging(Logging {
+        file: Some(file.to_str().unwrap().into()),
+        format: LogFormat::Json,
+        ..Default::default()
+    })
+    .unwrap();
+    let app = TestApp::new().await.map_router(|router| {
+        router
+            .layer(middleware::from_fn(logging::access_log))
+            .layer(middleware::from_fn(logging::request_id))
+    });
+
+    let supplied = "a".repeat(64);
+    assert_eq!(health(&app, Some(&supplied)).await, supplied);
+    let oversized = "b".repeat(65);
+    let replaced = health(&app, Some(&oversized)).await;
+    assert_ne!(replaced, oversized);
+    assert_eq!(replaced.len(), 32);
+    let generated = health(&app, None).await;
+    assert_eq!(generated.len(), 32);
+    assert_ne!(generated, replaced);
+
+    let content = std::fs::read_to_string(&file).unwrap();
+    let lines = content
+        .lines()
+        .map(|x| serde_json::from_str::<Value>(x).unwrap())
+        .filter(|x| x["target"] == logging::ACCESS_LOG_TARGET)
+        .collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{content}");
+    for (line, id) in lines.iter().zip([&supplied, &replaced, &generated]) {
+        assert_eq!(line["target"], "access");
+        assert_eq!(line["requestId"], id.as_str());
+        let message = line["message"].as_str().unwrap();
+        assert!(message.starts_with("- \"GET /health\" /health 200 "), "{message}");
+    }
+    std::fs::remove_file(file).unwrap();
+}
@@ -0,0 +1,61 @@
+//! Break history and MTBF with `GET /machine/:code/history`
+
+mod common;
+
+use axum::http::Method;
+use serde_json::Value;
+
+use common::{data, form, TestApp};
+
+async fn create_at(app: &TestApp, device_code: i32, creation_time: &str) {
+    let mut form = form(device_code, "1.0mm");
+    form.creation_time = creation_time.into();
+    data(app.send_form(Method::POST, "/inspection", &form).await);
+}
+
+async fn history(app: &TestApp, device_code: i32, window: &[(&str, &str)]) -> Value {
+    let query = serde_urlencoded::to_string(window).unwrap();
+    app.get(&format!("/machine/{device_code}/history?{query}")).await
+}
+
+#[tokio::test]
+async fn mtbf_within_windows() {
+    let app = TestApp::new().await;
+    for time in ["2026-10-19 08:00:00", "2026-10-19 09:00:00", "2026-10-19 11:00:00"] {
+        create_at(&app, 101, time).await;
+    }
+
+    let all = data(history(&app, 101, &[]).await);
+    assert_eq!(all["breakCount"], 3);
+    assert_eq!(all["breaks"].as_array().unwrap().len(), 3);
+    // three hours over two intervals
+    assert_eq!(all["mtbfSecs"], 5400.0);
+
+    let window = [("from", "2026-10-19 08:30:00"), ("to", "2026-10-19 11:00:00")];
+    let later = data(history(&app, 101, &window).await);
+    assert_eq!(later["breakCount"], 2);
+    assert_eq!(later["mtbfSecs"], 7200.0);
+
+    let window = [("from", "2026-10-19 10:00:00")];
+    let single = data(history(&app, 101, &window).await);
+    assert_eq!(single["breakCount"], 1);
+    assert_eq!(single["mtbfSecs"], Value::Null);
+
+    let window = [("from", "2026-01-01 00:00:00"), ("to", "2026-01-31 23:59:59")];
+    let empty = data(history(&app, 101, &window).await);
+    assert_eq!(empty["breakCount"], 0);
+    assert_eq!(empty["mtbfSecs"], Value::Null);
+    assert_eq!(empty["breaks"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn machine_without_breaks() {
+    let app = TestApp::new().await;
+    let idle = data(history(&app, 102, &[]).await);
+    assert_eq!(idle["stage"], 1);
+    assert_eq!(idle["breakCount"], 0);
+    assert_eq!(idle["mtbfSecs"], Value::Null);
+
+    let response = history(&app, 999, &[]).await;
+    assert_eq!(response["message"], "No such machine: 999");
+}
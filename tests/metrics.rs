@@ -0,0 +1,47 @@
+//! Prometheus metrics with `GET /metrics`
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::middleware;
+use czttgd_dao::metrics;
+
+use common::{data, form, TestApp};
+
+async fn scrape(app: &TestApp) -> String {
+    let request = Request::get("/metrics").body(Body::empty()).unwrap();
+    let (status, headers, body) = app.request_raw(request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(headers[header::CONTENT_TYPE].to_str().unwrap().starts_with("text/plain"));
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+// one test, as the metrics are global
+#[tokio::test]
+async fn expose_metrics() {
+    let app = TestApp::new()
+        .await
+        .map_router(|router| router.layer(middleware::from_fn(metrics::track)));
+    let id = data(app.send_form(Method::POST, "/inspection", &form(101, "1.0mm")).await);
+    let id = id.as_i64().unwrap();
+    let uri = format!("/inspection/{id}");
+    data(app.send_form(Method::PUT, &uri, &form(201, "1.2mm")).await);
+    // nothing to update
+    data(app.send_form(Method::PUT, "/inspection/1", &form(101, "1.2mm")).await);
+
+    let text = scrape(&app).await;
+    for line in [
+        "czttgd_inspections_total{operation=\"created\",stage=\"1\"} 1",
+        "czttgd_inspections_total{operation=\"updated\",stage=\"2\"} 1",
+        "czttgd_http_requests_total{method=\"POST\",route=\"/inspection\",status=\"200\"} 1",
+        "czttgd_http_requests_total{method=\"PUT\",route=\"/inspection/:id\",status=\"200\"} 2",
+        "czttgd_http_request_duration_seconds_count{method=\"PUT\",route=\"/inspection/:id\"} 2",
+        "czttgd_update_counter 2",
+    ] {
+        assert!(text.lines().any(|x| x == line), "{line} not in {text}");
+    }
+    assert!(!text.contains("operation=\"updated\",stage=\"1\""), "{text}");
+    assert!(text.contains("czttgd_http_request_duration_seconds_bucket{"), "{text}");
+    assert!(text.contains("czttgd_db_pool_max_connections "), "{text}");
+}
@@ -0,0 +1,58 @@
+//! The OpenAPI document at `GET /openapi.json`, against the routes listed
+//! by `GET /routes`
+
+mod common;
+
+use std::collections::BTreeSet;
+
+use axum::body::Body;
+use axum::http::Request;
+
+use common::TestApp;
+
+/// `(method, path)` of every route of a version, in the OpenAPI notation
+async fn routes(app: &TestApp, version: &str) -> BTreeSet<(String, String)> {
+    let request = Request::get("/routes").body(Body::empty()).unwrap();
+    let listing = app.request_raw(request).await.2;
+    let prefix = format!("/{version}");
+    std::str::from_utf8(&listing)
+        .unwrap()
+        .lines()
+        .filter(|x| !x.is_empty() && !x.starts_with('#'))
+        .map(|line| {
+            let (method, path) = line.split_once(' ').unwrap();
+            let path = path.strip_prefix(&prefix).unwrap();
+            let path = path
+                .split('/')
+                .map(|x| match x.strip_prefix(':') {
+                    Some(name) => format!("{{{name}}}"),
+                    None => x.into(),
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+            (method.to_lowercase(), path)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn document_every_route() {
+    let app = TestApp::new().await;
+    let routes = routes(&app, "v1").await;
+    assert!(routes.contains(&("get".into(), "/inspection/{id}/details".into())));
+
+    let document = app.get("/openapi.json").await;
+    let documented = document["paths"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .flat_map(|(path, operations)| {
+            let operations = operations.as_object().unwrap();
+            operations.keys().map(|method| (method.clone(), path.clone()))
+        })
+        .collect::<BTreeSet<_>>();
+    let undocumented = routes.difference(&documented).collect::<Vec<_>>();
+    assert!(undocumented.is_empty(), "Not in ApiDoc: {undocumented:?}");
+    let unrouted = documented.difference(&routes).collect::<Vec<_>>();
+    assert!(unrouted.is_empty(), "Not routed: {unrouted:?}");
+}
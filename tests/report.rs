@@ -0,0 +1,170 @@
+//! PDF reports with `GET /inspection/:id/report` and `GET /report/summary`
+
+mod common;
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use axum::body::{Body, Bytes};
+use axum::http::{header, HeaderMap, Method, Request, StatusCode};
+use czttgd_dao::config;
+use czttgd_dao::report::Font;
+use czttgd_dao::CONFIG;
+use flate2::read::ZlibDecoder;
+
+use common::{data, form, TestApp};
+
+/// Boxes for the characters of the reports; written by `report-font.py`
+/// next to it
+const FONT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/report-font.ttf");
+
+async fn pdf(app: &TestApp, uri: &str) -> (HeaderMap, Bytes) {
+    let request = Request::get(uri).body(Body::empty()).unwrap();
+    let (status, headers, body) = app.request_raw(request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[header::CONTENT_TYPE], "application/pdf");
+    assert!(body.starts_with(b"%PDF-"));
+    (headers, body)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|x| x == needle)
+}
+
+/// Glyph ids to characters, from the `ToUnicode` map of the font
+fn to_unicode(pdf: &[u8]) -> HashMap<u16, char> {
+    let start = find(pdf, b"beginbfchar\n").unwrap() + b"beginbfchar\n".len();
+    let end = start + find(&pdf[start..], b"endbfchar").unwrap();
+    let parse = |x: &str| u32::from_str_radix(x.trim_matches(['<', '>']), 16).unwrap();
+    std::str::from_utf8(&pdf[start..end])
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let (glyph, c) = line.split_once(' ').unwrap();
+            (parse(glyph) as u16, char::from_u32(parse(c)).unwrap())
+        })
+        .collect()
+}
+
+/// The operand of a `Tj` as written by `pdf-writer`
+fn string(operand: &[u8]) -> Vec<u8> {
+    if let Some(hex) = operand.strip_prefix(b"<") {
+        let hex = std::str::from_utf8(hex.strip_suffix(b">").unwrap()).unwrap();
+        return (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+    }
+    let mut bytes = operand[1..operand.len() - 1].iter().copied();
+    let mut string = vec![];
+    while let Some(byte) = bytes.next() {
+        if byte != b'\\' {
+            string.push(byte);
+            continue;
+        }
+        let escaped = bytes.next().unwrap();
+        string.push(match escaped {
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'b' => b'\x08',
+            b'f' => b'\x0c',
+            b'0'..=b'7' => {
+                let digits = [escaped, bytes.next().unwrap(), bytes.next().unwrap()];
+                u8::from_str_radix(std::str::from_utf8(&digits).unwrap(), 8).unwrap()
+            }
+            x => x,
+        });
+    }
+    string
+}
+
+/// The text shown on the pages, one string per `Tj`, with `�` for the
+/// characters missing from the font
+fn text(pdf: &[u8]) -> Vec<String> {
+    let glyphs = to_unicode(pdf);
+    let mut shown = vec![];
+    let mut rest = pdf;
+    while let Some(start) = find(rest, b"stream\n") {
+        rest = &rest[start + b"stream\n".len()..];
+        let end = find(rest, b"\nendstream").unwrap();
+        let stream = &rest[..end];
+        rest = &rest[end + b"\nendstream".len()..];
+        let mut content = vec![];
+        // the `ToUnicode` map is not compressed
+        if ZlibDecoder::new(stream).read_to_end(&mut content).is_err() {
+            continue;
+        }
+        for operand in content.split(|&x| x == b'\n').filter_map(|x| x.strip_suffix(b" Tj")) {
+            let string = string(operand);
+            let line = string
+                .chunks(2)
+                .map(|x| {
+                    let glyph = u16::from_be_bytes([x[0], x[1]]);
+                    glyphs.get(&glyph).copied().unwrap_or(char::REPLACEMENT_CHARACTER)
+                })
+                .collect();
+            shown.push(line);
+        }
+    }
+    shown
+}
+
+#[tokio::test]
+async fn reports() {
+    CONFIG.lock().unwrap().report.font = Some(FONT.into());
+    let app = TestApp::new().await;
+    let id = data(app.send_form(Method::POST, "/inspection", &form(101, "1.0mm")).await);
+    let id = id.as_i64().unwrap();
+    // another stage
+    data(app.send_form(Method::POST, "/inspection", &form(201, "1.0mm")).await);
+
+    let (_, record) = pdf(&app, &format!("/inspection/{id}/report")).await;
+    // embedded as a subset
+    assert!(find(&record, b"FontFile2").is_some());
+    assert!(record.len() < 100_000, "{}", record.len());
+    let shown = text(&record);
+    assert!(!shown.iter().any(|x| x.contains(char::REPLACEMENT_CHARACTER)), "{shown:?}");
+    for line in ["断线检验记录", "状态：初检", "机台号", "101", "拉丝机", "张三", "杂质"] {
+        assert!(shown.iter().any(|x| x == line), "{line} not in {shown:?}");
+    }
+
+    let uri = "/report/summary?stage=1&from=2026-10-19&to=2026-10-19";
+    let (headers, summary) = pdf(&app, uri).await;
+    assert_eq!(
+        headers[header::CONTENT_DISPOSITION],
+        "inline; filename=\"summary-1-2026-10-19-2026-10-19.pdf\""
+    );
+    let shown = text(&summary);
+    for line in [
+        "断线汇总",
+        "工序：1    期间：2026-10-19 至 2026-10-19",
+        "合计 1 条；初检 1 条，终检 0 条",
+        "拉丝池 0 条，非拉丝池 1 条",
+        "杂质",
+        "1 条",
+        "2026-10-19 08:30:00",
+        "101",
+    ] {
+        assert!(shown.iter().any(|x| x == line), "{line} not in {shown:?}");
+    }
+    assert!(!shown.iter().any(|x| x == "201"), "{shown:?}");
+    assert!(!shown.iter().any(|x| x.contains(char::REPLACEMENT_CHARACTER)), "{shown:?}");
+
+    let uri = "/report/summary?stage=1&from=2026-10-18&to=2026-10-18";
+    let shown = text(&pdf(&app, uri).await.1);
+    assert!(shown.iter().any(|x| x == "合计 0 条；初检 0 条，终检 0 条"), "{shown:?}");
+
+    let response = app.get("/inspection/1/report").await;
+    assert_eq!(response["message"], "No such inspection: 1");
+}
+
+#[tokio::test]
+async fn reject_invalid_reports() {
+    let error = Font::load(&config::Report::default()).await.err().unwrap();
+    assert_eq!(error.to_string(), "No report font configured");
+
+    let app = TestApp::new().await;
+    let response = app.get("/report/summary?stage=1&from=2024-01-01&to=yesterday").await;
+    assert_eq!(response["message"], "Invalid date: yesterday");
+}
@@ -0,0 +1,84 @@
+//! Handlers over the in-memory repositories, so these run without MySQL
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::Method;
+use czttgd_dao::repository::InspectionRepository;
+use serde_json::{json, Value};
+
+use common::memory::MemoryRepository;
+use common::{data, form, TestApp};
+
+fn app() -> (TestApp, Arc<MemoryRepository>) {
+    let repository = Arc::new(MemoryRepository::default());
+    (TestApp::in_memory(repository.clone()), repository)
+}
+
+fn ids(response: Value) -> Vec<i64> {
+    let summaries = data(response);
+    let summaries = summaries.as_array().unwrap();
+    summaries.iter().map(|x| x["id"].as_i64().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn master_data() {
+    let (app, _) = app();
+    let users = data(app.get("/users").await);
+    assert_eq!(users[1]["name"], "李四");
+    let causes = data(app.get("/break/causes").await);
+    assert_eq!(causes[0]["cause"], "导轮磨损");
+    let breakpoints = data(app.get("/break/points").await);
+    assert_eq!(breakpoints[1]["breakpoint"], "出线口");
+    assert_eq!(data(app.get("/stage/1/devices").await), json!([101, 102]));
+    assert_eq!(data(app.get("/v1/stage/2/devices").await), json!([201]));
+}
+
+#[tokio::test]
+async fn post_update_and_query_details() {
+    let (app, _) = app();
+    let id = data(app.send_form(Method::POST, "/inspection", &form(101, "1.0mm")).await);
+
+    let mut changed = form(102, "2.0mm");
+    changed.breakpoint_b = Some("12.5".into());
+    let uri = format!("/inspection/{id}");
+    data(app.send_form(Method::PUT, &uri, &changed).await);
+
+    let details = data(app.get(&format!("/inspection/{id}/details")).await);
+    assert_eq!(details["deviceCode"], 102);
+    assert_eq!(details["creator"]["name"], "张三");
+    assert_eq!(details["breakpointA"]["breakpoint"], "进线口");
+    assert_eq!(details["breakpointB"], "12.5");
+    assert_eq!(details["breakCauseA"]["cause"], "杂质");
+    assert_eq!(data(app.get("/inspection/count").await), 1);
+}
+
+#[tokio::test]
+async fn query_details_of_missing_inspection() {
+    let (app, _) = app();
+    let response = app.get("/inspection/42/details").await;
+    assert_eq!(response["code"], 1);
+    assert_eq!(response["message"], "No such inspection: 42");
+}
+
+#[tokio::test]
+async fn search() {
+    let (app, repository) = app();
+    let mut posted = vec![];
+    for (device_code, spec) in [(101, "1.0mm"), (102, "2.0mm"), (201, "1.0mm"), (101, "3.0mm")] {
+        let response = app.send_form(Method::POST, "/inspection", &form(device_code, spec)).await;
+        posted.push(data(response).as_i64().unwrap());
+    }
+    repository.set_deleted(posted[3], true).await.unwrap();
+
+    let search = |query: &str| format!("/inspection/search?{query}");
+    let found = ids(app.get(&search("stage=1&filter=")).await);
+    assert_eq!(found, vec![posted[1], posted[0]]);
+    let found = ids(app.get(&search("stage=2&filter=")).await);
+    assert_eq!(found, vec![posted[2]]);
+    let found = ids(app.get(&search("stage=1&filter=2.0mm")).await);
+    assert_eq!(found, vec![posted[1]]);
+    let found = ids(app.get(&search("stage=1&filter=&limit=1&offset=1")).await);
+    assert_eq!(found, vec![posted[0]]);
+}
@@ -0,0 +1,180 @@
+//! Scheduled summaries, written into a directory and mailed
+
+mod common;
+
+use axum::http::Method;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use czttgd_dao::config::{self, Smtp, SmtpSecurity};
+use czttgd_dao::repository::TIME_FORMAT;
+use czttgd_dao::summary::{self, Summary};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+use common::{form, TestApp};
+
+/// Every midnight
+const SCHEDULE: &str = "0 0 * * *";
+
+/// The midnight after the `creation_time` of [`form`], ending the period
+/// that covers what the tests create
+fn end() -> DateTime<Local> {
+    let created = form(101, "1.0mm").creation_time;
+    let created = NaiveDateTime::parse_from_str(&created, TIME_FORMAT).unwrap();
+    let midnight = created.date().succ_opt().unwrap().and_hms_opt(0, 0, 0).unwrap();
+    Local.from_local_datetime(&midnight).unwrap()
+}
+
+async fn create(app: &TestApp, device_code: i32) {
+    app.send_form(Method::POST, "/inspection", &form(device_code, "1.0mm")).await;
+}
+
+/// Runs the period ending at [`end`].
+async fn run(app: &TestApp, config: &config::Summary) {
+    let schedule = summary::parse_schedule(SCHEDULE).unwrap();
+    let repositories = app.db().repositories();
+    summary::run_once(
+        repositories.inspections.as_ref(),
+        repositories.master_data.as_ref(),
+        config,
+        &schedule,
+        end(),
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn count_by_stage() {
+    let app = TestApp::new().await;
+    for device_code in [101, 101, 102, 201] {
+        create(&app, device_code).await;
+    }
+    let repositories = app.db().repositories();
+    let schedule = summary::parse_schedule(SCHEDULE).unwrap();
+    let start = schedule.after(&end()).next_back().unwrap();
+    let summaries = summary::summarize(
+        repositories.inspections.as_ref(),
+        repositories.master_data.as_ref(),
+        &[1, 2],
+        start,
+        end(),
+    )
+    .await
+    .unwrap();
+
+    let [first, second]: [Summary; 2] = summaries.try_into().ok().unwrap();
+    assert_eq!((first.total, first.finalized), (3, 0));
+    assert_eq!(first.by_machine, vec![(101, 2), (102, 1)]);
+    assert_eq!(second.total, 1);
+    assert_eq!(second.by_machine, vec![(201, 1)]);
+    assert_eq!(first.from, "2026-10-19 00:00:00");
+    assert_eq!(first.to, "2026-10-20 00:00:00");
+
+    // nothing from the previous period
+    let summaries = summary::summarize(
+        repositories.inspections.as_ref(),
+        repositories.master_data.as_ref(),
+        &[1],
+        schedule.after(&start).next_back().unwrap(),
+        start,
+    )
+    .await
+    .unwrap();
+    assert_eq!(summaries[0].total, 0);
+}
+
+#[tokio::test]
+async fn write_into_dir() {
+    let app = TestApp::new().await;
+    create(&app, 101).await;
+    let dir = std::env::temp_dir().join(format!("czttgd_summary_{}", uuid::Uuid::new_v4()));
+    let config = config::Summary {
+        stages: vec![1],
+        dir: Some(dir.to_str().unwrap().into()),
+        ..Default::default()
+    };
+    run(&app, &config).await;
+
+    let mut files = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|x| x.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<_>>();
+    files.sort();
+    assert_eq!(files, ["summary-1-20261020-0000.csv", "summary-1-20261020-0000.html"]);
+    let csv = std::fs::read_to_string(dir.join(&files[0])).unwrap();
+    assert!(csv.contains("合计,,1\n"));
+    assert!(csv.contains("机台号,101,1\n"));
+    let html = std::fs::read_to_string(dir.join(&files[1])).unwrap();
+    assert!(html.contains("<h2>工序 1</h2>"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Accepts one message and returns the whole conversation from the client.
+async fn smtp_stand_in(listener: TcpListener) -> String {
+    let (stream, _) = listener.accept().await.unwrap();
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut received = String::new();
+    writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+    let mut in_data = false;
+    while let Some(line) = lines.next_line().await.unwrap() {
+        received.push_str(&line);
+        received.push('\n');
+        let reply: &[u8] = if in_data {
+            if line != "." {
+                continue;
+            }
+            in_data = false;
+            b"250 Queued\r\n"
+        } else if line.starts_with("DATA") {
+            in_data = true;
+            b"354 Go ahead\r\n"
+        } else if line.starts_with("QUIT") {
+            writer.write_all(b"221 Bye\r\n").await.unwrap();
+            break;
+        } else {
+            b"250 OK\r\n"
+        };
+        writer.write_all(reply).await.unwrap();
+    }
+    received
+}
+
+#[tokio::test]
+async fn send_mail() {
+    let app = TestApp::new().await;
+    create(&app, 201).await;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = tokio::spawn(smtp_stand_in(listener));
+
+    let config = config::Summary {
+        smtp: Some(Smtp {
+            host: "127.0.0.1".into(),
+            port: Some(port),
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+            from: "断线汇总 <czttgd@example.com>".into(),
+            to: vec!["leader@example.com".into()],
+        }),
+        ..Default::default()
+    };
+    run(&app, &config).await;
+
+    let received = server.await.unwrap();
+    assert!(received.contains("MAIL FROM:<czttgd@example.com>"), "{received}");
+    assert!(received.contains("RCPT TO:<leader@example.com>"), "{received}");
+    assert!(received.contains("Content-Type: text/html"), "{received}");
+    // one attachment per stage
+    assert_eq!(received.matches("Content-Type: text/csv").count(), 2, "{received}");
+    assert!(received.contains("filename=\"summary-2-20261020-0000.csv\""), "{received}");
+}
+
+#[test]
+fn parse_schedules() {
+    assert!(summary::parse_schedule("0 8,16 * * Mon-Sat").is_ok());
+    assert!(summary::parse_schedule("30 0 8 * * *").is_ok());
+    let error = summary::parse_schedule("every shift").err().unwrap();
+    assert!(error.to_string().starts_with("Invalid schedule every shift"));
+}
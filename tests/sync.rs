@@ -0,0 +1,190 @@
+//! The offline sync endpoints
+
+mod common;
+
+use axum::http::Method;
+use czttgd_api::{Changes, InspectionChange, SyncItem, SyncResult, SyncStatus};
+use czttgd_dao::metrics::INSPECTIONS;
+use czttgd_dao::repository::NewAttachment;
+use serde_json::Value;
+
+use common::{data, form, TestApp};
+
+const A: &str = "0b7c61c4-5c4e-4d8e-9a55-2f1d7e0f4a01";
+const B: &str = "0b7c61c4-5c4e-4d8e-9a55-2f1d7e0f4a02";
+
+fn item(client_id: &str, base_version: Option<i64>, spec: &str) -> SyncItem {
+    SyncItem {
+        client_id: client_id.into(),
+        base_version,
+        form: form(101, spec),
+    }
+}
+
+async fn push(app: &TestApp, items: &[SyncItem]) -> Vec<SyncResult> {
+    let response = app.send_json(Method::POST, "/sync/inspections", &items).await;
+    serde_json::from_value(data(response)).unwrap()
+}
+
+async fn changes(app: &TestApp, since: i64, limit: u64) -> Changes {
+    let uri = format!("/sync/changes?since={since}&limit={limit}");
+    serde_json::from_value(data(app.get(&uri).await)).unwrap()
+}
+
+async fn spec(app: &TestApp, id: i64) -> Value {
+    let details = data(app.get(&format!("/inspection/{id}/details")).await);
+    details["productSpec"].clone()
+}
+
+#[tokio::test]
+async fn push_is_idempotent() {
+    let app = TestApp::new().await;
+    let items = [item(A, None, "1.0mm"), item(B, None, "2.0mm")];
+    let first = push(&app, &items).await;
+    assert_eq!(first[0].status, SyncStatus::Created);
+    assert_eq!(first[1].status, SyncStatus::Created);
+    assert_ne!(first[0].id, first[1].id);
+    assert!(first[0].version < first[1].version);
+
+    let again = push(&app, &items).await;
+    for (a, b) in again.iter().zip(&first) {
+        assert_eq!(a.status, SyncStatus::Unchanged);
+        assert_eq!((a.id, a.version), (b.id, b.version));
+    }
+    assert_eq!(data(app.get("/inspection/count").await), 2);
+}
+
+#[tokio::test]
+async fn client_ids_are_normalized() {
+    let app = TestApp::new().await;
+    let created = push(&app, &[item(&A.to_uppercase(), None, "1.0mm")]).await;
+    assert_eq!(created[0].client_id, A);
+    let again = push(&app, &[item(A, None, "1.0mm")]).await;
+    assert_eq!(again[0].status, SyncStatus::Unchanged);
+
+    let response = app
+        .send_json(Method::POST, "/sync/inspections", &[item("tablet-1", None, "1.0mm")])
+        .await;
+    assert_eq!(response["code"], 1);
+    assert_eq!(response["message"], "Invalid client id: tablet-1");
+}
+
+#[tokio::test]
+async fn limit_pushes() {
+    let app = TestApp::new().await;
+    let items = (0..1001)
+        .map(|_| item(&uuid::Uuid::new_v4().to_string(), None, "1.0mm"))
+        .collect::<Vec<_>>();
+    let response = app.send_json(Method::POST, "/sync/inspections", &items).await;
+    assert_eq!(response["message"], "Expected at most 1000 items");
+    assert_eq!(data(app.get("/inspection/count").await), 0);
+
+    // the only updates on stage 2
+    let updated = || INSPECTIONS.with_label_values(&["updated", "2"]).get();
+    let mut items = items[..3].to_vec();
+    for item in &mut items {
+        item.form.device_code = 201;
+    }
+    let pushed = push(&app, &items).await;
+    let edit = SyncItem {
+        base_version: Some(pushed[0].version),
+        form: form(201, "2.0mm"),
+        ..items[0].clone()
+    };
+    let edited = push(&app, &[edit.clone(), items[1].clone()]).await;
+    assert_eq!(edited[0].status, SyncStatus::Updated);
+    assert_eq!(updated(), 1);
+    push(&app, &[edit]).await;
+    assert_eq!(updated(), 1);
+}
+
+#[tokio::test]
+async fn server_wins_over_stale_edits() {
+    let app = TestApp::new().await;
+    let created = &push(&app, &[item(A, None, "1.0mm")]).await[0];
+    let id = created.id;
+
+    // the client edits its own inspection freely
+    let edited = &push(&app, &[item(A, None, "1.5mm")]).await[0];
+    assert_eq!(edited.status, SyncStatus::Updated);
+    assert_eq!(edited.id, id);
+    assert!(edited.version > created.version);
+
+    // until someone else changes it
+    let uri = format!("/inspection/{id}");
+    data(app.send_form(Method::PUT, &uri, &form(101, "2.0mm")).await);
+    let stale = &push(&app, &[item(A, Some(edited.version), "3.0mm")]).await[0];
+    assert_eq!(stale.status, SyncStatus::Conflict);
+    assert_eq!(spec(&app, id).await, "2.0mm");
+
+    // an edit based on what the server has now goes through
+    let rebased = &push(&app, &[item(A, Some(stale.version), "3.0mm")]).await[0];
+    assert_eq!(rebased.status, SyncStatus::Updated);
+    assert_eq!(spec(&app, id).await, "3.0mm");
+}
+
+#[tokio::test]
+async fn changes_since_a_version() {
+    let app = TestApp::new().await;
+    let posted = data(app.send_form(Method::POST, "/inspection", &form(201, "1.0mm")).await);
+    let pushed = push(&app, &[item(A, None, "2.0mm")]).await;
+
+    let all = changes(&app, 0, 100).await;
+    assert!(!all.more);
+    let ids = all.changes.iter().map(|x| x.inspection.id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![posted.as_i64().unwrap(), pushed[0].id]);
+    assert_eq!(all.changes[0].client_id, None);
+    assert_eq!(all.changes[1].client_id.as_deref(), Some(A));
+    assert_eq!(all.version, pushed[0].version);
+
+    let page = changes(&app, 0, 1).await;
+    assert!(page.more);
+    assert_eq!(page.changes.len(), 1);
+    let rest = changes(&app, page.version, 1).await;
+    assert!(!rest.more);
+    assert_eq!(rest.changes[0].inspection.id, pushed[0].id);
+
+    let none = changes(&app, all.version, 100).await;
+    assert!(none.changes.is_empty());
+    assert_eq!(none.version, all.version);
+
+    // a deletion moves the inspection to the end
+    let inspections = app.db().repositories().inspections;
+    assert!(inspections.set_deleted(posted.as_i64().unwrap(), true).await.unwrap());
+    let deleted = changes(&app, all.version, 100).await;
+    assert_eq!(deleted.changes.len(), 1);
+    assert!(deleted.changes[0].deleted);
+    assert_eq!(deleted.changes[0].inspection.id, posted.as_i64().unwrap());
+}
+
+#[tokio::test]
+async fn changes_carry_attachments() {
+    let app = TestApp::new().await;
+    let pushed = push(&app, &[item(A, None, "1.0mm"), item(B, None, "2.0mm")]).await;
+    let attachments = app.db().repositories().attachments;
+    for (id, name) in [(pushed[0].id, "a1"), (pushed[1].id, "b1"), (pushed[0].id, "a2")] {
+        let attachment = NewAttachment {
+            inspection_id: id,
+            filename: Some(format!("{name}.jpg")),
+            content_type: "image/jpeg".into(),
+            size: 1,
+            width: 1,
+            height: 1,
+            object_key: name.into(),
+            thumbnail_key: format!("{name}-thumbnail"),
+        };
+        attachments.insert(attachment).await.unwrap();
+    }
+    let filenames = |change: &InspectionChange| {
+        let attachments = &change.inspection.attachments;
+        attachments.iter().map(|x| x.filename.clone().unwrap()).collect::<Vec<_>>()
+    };
+
+    let all = changes(&app, 0, 100).await;
+    assert_eq!(filenames(&all.changes[0]), ["a1.jpg", "a2.jpg"]);
+    assert_eq!(filenames(&all.changes[1]), ["b1.jpg"]);
+    let page = changes(&app, 0, 1).await;
+    assert_eq!(filenames(&page.changes[0]), ["a1.jpg", "a2.jpg"]);
+    let rest = changes(&app, page.version, 1).await;
+    assert_eq!(filenames(&rest.changes[0]), ["b1.jpg"]);
+}
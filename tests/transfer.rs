@@ -0,0 +1,60 @@
+//! Copying the data between databases, as `czttgd copy` does
+
+mod common;
+
+use axum::http::Method;
+use czttgd_dao::repository::Repositories;
+use serde_json::Value;
+
+use common::{data, form, TestApp, TestDb};
+
+fn target_name() -> String {
+    format!("czttgd_test_{}", uuid::Uuid::new_v4().simple())
+}
+
+#[tokio::test]
+async fn copy_into_empty_sqlite() {
+    let app = TestApp::new().await;
+    let mut ids = vec![];
+    for device_code in [101, 201] {
+        let response = app.send_form(Method::POST, "/inspection", &form(device_code, "1.0mm")).await;
+        ids.push(data(response).as_i64().unwrap());
+    }
+    let source = app.db().repositories();
+    assert!(source.inspections.set_deleted(ids[1], true).await.unwrap());
+
+    let target = TestDb::empty_sqlite(target_name()).await;
+    let dump = source.transfer.dump().await.unwrap();
+    target.db.repositories().transfer.load(&dump).await.unwrap();
+
+    let copied = target.db.repositories();
+    assert_eq!(copied.master_data.users().await.unwrap().len(), 2);
+    assert_eq!(copied.master_data.devices(1).await.unwrap(), vec![101, 102]);
+    assert_eq!(copied.inspections.serial().await.unwrap(), 3);
+    assert_eq!(copied.inspections.count().await.unwrap(), 2);
+    let version = |r: &Repositories| {
+        let sync = r.sync.clone();
+        async move { sync.changes(0, 100).await.unwrap().version }
+    };
+    assert_eq!(version(&copied).await, version(&source).await);
+    // deleted ones stay deleted
+    assert!(!copied.inspections.set_deleted(ids[1], true).await.unwrap());
+
+    assert_eq!(details(&target, ids[0]).await, details(app.test_db(), ids[0]).await);
+}
+
+async fn details(db: &TestDb, id: i64) -> Value {
+    let details = db.db.repositories().inspections.details(id).await.unwrap();
+    serde_json::to_value(details.unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn copy_refuses_non_empty_target() {
+    let app = TestApp::new().await;
+    let dump = app.db().repositories().transfer.dump().await.unwrap();
+
+    let target = TestDb::create().await;
+    let result = target.db.repositories().transfer.load(&dump).await;
+    assert!(result.is_err());
+    assert_eq!(target.db.repositories().inspections.count().await.unwrap(), 0);
+}
@@ -0,0 +1,38 @@
+//! API versions, mounted at `/<version>`, with the first one also at the root
+
+mod common;
+
+use axum::body::Body;
+use axum::http::{Method, Request, StatusCode};
+
+use common::{data, form, TestApp};
+
+#[tokio::test]
+async fn alias_v1_at_root() {
+    let app = TestApp::new().await;
+    let users = data(app.get("/v1/users").await);
+    assert_eq!(users.as_array().unwrap().len(), 2);
+    assert_eq!(data(app.get("/users").await), users);
+
+    // the same handlers over the same state
+    let id = data(app.send_form(Method::POST, "/v1/inspection", &form(101, "1.0mm")).await);
+    let details = data(app.get(&format!("/inspection/{id}/details")).await);
+    assert_eq!(details["deviceCode"], 101);
+    assert_eq!(data(app.get("/v1/inspection/count").await), 1);
+    assert_eq!(data(app.get("/inspection/count").await), 1);
+
+    let request = Request::get("/routes").body(Body::empty()).unwrap();
+    let listing = app.request_raw(request).await.2;
+    let listing = std::str::from_utf8(&listing).unwrap();
+    assert!(listing.starts_with("# v1 (also at /)\n"), "{listing}");
+    assert!(listing.contains("\nGET /v1/users\n"), "{listing}");
+}
+
+#[tokio::test]
+async fn reject_unknown_versions() {
+    let app = TestApp::new().await;
+    for uri in ["/v2/users", "/v0/users", "/v1/v1/users"] {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+        assert_eq!(app.request_raw(request).await.0, StatusCode::NOT_FOUND, "{uri}");
+    }
+}